@@ -1,4 +1,4 @@
-use game_state::{DBStatsMessage, PlayerState};
+use game_state::{DBStatsMessage, MatchResult, PlayerState};
 use serde::Serialize;
 use std::future::Future;
 
@@ -60,6 +60,10 @@ impl GameDatabase {
                         db.increment_player_stats(&DBPlayer::from_player_state(&player))
                             .expect("Failed to update players");
                     }
+                    DBStatsMessage::MatchResult(result) => {
+                        db.insert_match_result(&result)
+                            .expect("Failed to insert match result");
+                    }
                 }
             }
         };
@@ -80,6 +84,18 @@ impl GameDatabase {
              )",
             rusqlite::params![],
         )?;
+
+        conn.execute(
+            "create table if not exists matches (
+                 id integer primary key autoincrement,
+                 server text,
+                 round integer,
+                 seed integer,
+                 winner text,
+                 players integer
+             )",
+            rusqlite::params![],
+        )?;
         Ok(Self { conn })
     }
 
@@ -140,6 +156,31 @@ impl GameDatabase {
         Ok(())
     }
 
+    fn insert_match_result(&mut self, result: &MatchResult) -> anyhow::Result<()> {
+        let winner = result.winner.as_ref().map(|player| player.name.clone());
+        self.conn.execute(
+            "insert into matches (server, round, seed, winner, players) values (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                result.server,
+                result.round,
+                result.seed,
+                winner,
+                result.players.len()
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn count_wins(&self, name: &str) -> anyhow::Result<usize> {
+        let wins = self.conn.query_row(
+            "select count(*) from matches where winner = ?1",
+            rusqlite::params![name],
+            |row| row.get(0),
+        )?;
+        Ok(wins)
+    }
+
     fn get_player(&self, name: &str) -> anyhow::Result<DBPlayer> {
         let mut stmt = self.conn.prepare("select * from players where name = ?1")?;
         let mut rows = stmt.query(rusqlite::params![name])?;
@@ -164,6 +205,7 @@ impl GameDatabase {
 #[cfg(test)]
 mod test {
     use crate::database::DBPlayer;
+    use game_state::{MatchResult, PlayerState};
 
     use super::GameDatabase;
 
@@ -177,4 +219,20 @@ mod test {
         assert_eq!(player.kills, 0);
         assert_eq!(player.deaths, 0);
     }
+
+    #[test]
+    fn test_match_result() {
+        let mut db = GameDatabase::in_memory().unwrap();
+        let winner = PlayerState::new("test".to_string(), 1, "br".to_string());
+        let result = MatchResult {
+            server: "server".to_string(),
+            round: 1,
+            seed: 5,
+            winner: Some(winner.clone()),
+            players: vec![winner],
+        };
+        db.insert_match_result(&result).unwrap();
+        assert_eq!(db.count_wins("test").unwrap(), 1);
+        assert_eq!(db.count_wins("other").unwrap(), 0);
+    }
 }
@@ -11,7 +11,10 @@ use futures::{
     SinkExt,
 };
 use futures_util::StreamExt;
-use game_state::{DBStatsMessage, TICK_TIME};
+use game_state::{
+    BotBrainKind, BotConfig, BotDifficulty, BotPersonality, DBStatsMessage, MatchRules,
+    ServerConfig, TICK_TIME,
+};
 use server_pool::ServerPool;
 use std::sync::{Arc, Mutex, MutexGuard};
 use tower_http::{
@@ -23,6 +26,8 @@ mod database;
 mod server_pool;
 
 const DB_PATH: &str = "./data/game.db";
+const MATCH_TIME_LIMIT: f64 = 20.0 * 60.0;
+const MAP_CONTROL_TO_WIN: f64 = 60.0;
 
 #[derive(Clone)]
 struct Apps {
@@ -33,9 +38,18 @@ struct Apps {
 impl Apps {
     fn new(db_sender: Sender<DBStatsMessage>) -> Apps {
         let mut pool = ServerPool::new(db_sender);
-        pool.create_server("AWS SP1", 5)
+        let timed_match = ServerConfig {
+            match_rules: MatchRules {
+                time_limit: Some(MATCH_TIME_LIMIT),
+                map_control_to_win: Some(MAP_CONTROL_TO_WIN),
+                last_player_standing: false,
+            },
+            pirate_intensity: 1.0,
+            ..Default::default()
+        };
+        pool.create_server("AWS SP1", 5, timed_match)
             .expect("Failed to create default server");
-        pool.create_server("AWS SP2", 1)
+        pool.create_server("AWS SP2", 1, ServerConfig::default())
             .expect("Failed to create default server");
 
         let stats_db = GameDatabase::file(DB_PATH).expect("Failed to create db");
@@ -121,6 +135,7 @@ struct WsQuery {
     player_name: Option<String>,
     player_id: Option<u64>,
     flag: Option<String>,
+    team: Option<u8>,
 }
 
 async fn ws_handler(
@@ -132,6 +147,7 @@ async fn ws_handler(
     let player_name = params.player_name.clone().unwrap_or("Unknown".to_string());
     let player_id = params.player_id.clone();
     let flag = params.flag.clone();
+    let team = params.team;
     log::info!("Connecting {player_name} Player to server {server_id}");
     let res = ws.on_upgrade(move |ws| {
         return async move {
@@ -150,7 +166,7 @@ async fn ws_handler(
             });
             let id = {
                 if let Some(server) = state.get_game_server().get_server(&server_id) {
-                    server.new_connection(player_send, player_id, &player_name, flag)
+                    server.new_connection(player_send, player_id, &player_name, flag, team)
                 } else {
                     log::warn!("Server {server_id} not found, disconnecting player {player_name}");
                     return;
@@ -238,15 +254,52 @@ async fn handle_post_error(body: Json<serde_json::Value>) -> impl IntoResponse {
 struct CreateServerParams {
     server_id: String,
     server_seed: u32,
+    time_limit: Option<f64>,
+    map_control_to_win: Option<f64>,
+    #[serde(default)]
+    last_player_standing: bool,
+    #[serde(default)]
+    teams: u8,
+    friendly_fire: Option<bool>,
+    #[serde(default)]
+    pirate_intensity: f64,
+    ship_cap: Option<usize>,
+    #[serde(default)]
+    ship_cap_per_island: usize,
+    upkeep_free_ships: Option<usize>,
+    #[serde(default)]
+    bot_difficulty: BotDifficulty,
+    #[serde(default)]
+    bot_personality: BotPersonality,
+    #[serde(default)]
+    bot_brain: BotBrainKind,
 }
 
 async fn create_server_handler(
     params: Query<CreateServerParams>,
     state: State<AppState>,
 ) -> impl IntoResponse {
+    let config = ServerConfig {
+        match_rules: MatchRules {
+            time_limit: params.time_limit,
+            map_control_to_win: params.map_control_to_win,
+            last_player_standing: params.last_player_standing,
+        },
+        teams: params.teams,
+        friendly_fire: params.friendly_fire.unwrap_or(true),
+        pirate_intensity: params.pirate_intensity,
+        ship_cap: params.ship_cap.unwrap_or(ServerConfig::default().ship_cap),
+        ship_cap_per_island: params.ship_cap_per_island,
+        upkeep_free_ships: params.upkeep_free_ships,
+        bots: BotConfig {
+            difficulty: params.bot_difficulty,
+            personality: params.bot_personality,
+            brain: params.bot_brain,
+        },
+    };
     match state
         .get_game_server()
-        .create_server(&params.server_id, params.server_seed)
+        .create_server(&params.server_id, params.server_seed, config)
     {
         Ok(_) => {
             let server_id = params.server_id.clone();
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use futures::channel::mpsc::Sender;
-use game_state::{DBStatsMessage, GameServer};
+use game_state::{DBStatsMessage, GameServer, ServerConfig};
 use std::{collections::HashMap, time::Duration};
 
 const MAX_SERVERS: usize = 3;
@@ -77,12 +77,18 @@ impl ServerPool {
         return Ok(());
     }
 
-    pub fn create_server(&mut self, server_id: &str, seed: u32) -> Result<()> {
+    pub fn create_server(
+        &mut self,
+        server_id: &str,
+        seed: u32,
+        config: ServerConfig,
+    ) -> Result<()> {
         if self.servers.len() >= MAX_SERVERS {
             return Err(anyhow::anyhow!("Max servers reached"));
         }
         let mut server = GameServer::new(Some(self.db_sender.clone()), seed);
         server.name = server_id.to_string();
+        server.set_config(config);
         self.servers.insert(server_id.to_string(), server);
         return Ok(());
     }
@@ -12,16 +12,34 @@ export class IslandsManager {
   owners: IslandOwners;
   islandData: Map<number, IslandData>;
   needsUpdate = false;
+  private spriteGroup: THREE.Group;
+  private lighthouse?: THREE.Object3D;
 
   constructor(readonly game: GameWasmState, readonly scene: THREE.Scene) {
     const { spriteMap, spriteGroup, owners, islandData } = this.makeFlags();
     this.islandData = new Map(islandData.map((island) => [island.id, island]));
     this.owners = owners;
     this.flagSprites = spriteMap;
+    this.spriteGroup = spriteGroup;
     this.scene.add(spriteGroup);
     this.loadLighthouse();
   }
 
+  /** A new round generated another world, flags and lighthouses move to the new islands */
+  private rebuild() {
+    this.scene.remove(this.spriteGroup);
+    this.flagSprites.forEach((sprite) => sprite.material.dispose());
+    const { spriteMap, spriteGroup, owners, islandData } = this.makeFlags();
+    this.islandData = new Map(islandData.map((island) => [island.id, island]));
+    this.owners = owners;
+    this.flagSprites = spriteMap;
+    this.spriteGroup = spriteGroup;
+    this.scene.add(spriteGroup);
+    if (this.lighthouse) {
+      this.placeLighthouses(this.lighthouse);
+    }
+  }
+
   getFlagTexture(country: string) {
     const flag = getFlagTexture(country);
     if (flag) {
@@ -48,17 +66,26 @@ export class IslandsManager {
       lighthouse.position.set(0, 0, 0);
       headlightMesh.position.set(0, 5.1, 0);
       lighthouse.add(headlightMesh);
-      this.islandData.forEach((island) => {
-        const [x, y] = island.light_house;
-        const lighthouseInstance = lighthouse.clone();
-        lighthouseInstance.position.set(x, y, 0);
-        this.lightHouseGroup.add(lighthouseInstance);
-      });
+      this.lighthouse = lighthouse;
+      this.placeLighthouses(lighthouse);
     });
     this.scene.add(this.lightHouseGroup);
   }
 
+  private placeLighthouses(lighthouse: THREE.Object3D) {
+    this.lightHouseGroup.clear();
+    this.islandData.forEach((island) => {
+      const [x, y] = island.light_house;
+      const lighthouseInstance = lighthouse.clone();
+      lighthouseInstance.position.set(x, y, 0);
+      this.lightHouseGroup.add(lighthouseInstance);
+    });
+  }
+
   tick() {
+    if (this.game.has_world_changed()) {
+      this.rebuild();
+    }
     if (!this.game.has_map_changed() && !this.needsUpdate) {
       return;
     }
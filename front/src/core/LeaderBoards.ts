@@ -1,6 +1,6 @@
 import { GameWasmState } from "rust";
 import { getFlagImage } from "./PlayerStuff";
-import { PlayerInfo } from "./RustWorldTypes";
+import { MatchInfo, PlayerInfo } from "./RustWorldTypes";
 
 const PADDING = 5;
 const HEIGHT = 300;
@@ -65,8 +65,10 @@ export class LeaderBoards {
     const imagePadding = 4;
 
     const playersArr = [...players.values()];
+    const matchLine = matchFormat(this.game.match_info(), playersArr);
+    const lines = playersArr.length + 1 + (matchLine ? 1 : 0);
     ctx.fillStyle = "#00000088";
-    const totalHeight = (playersArr.length + 1) * lineHeight + 2 * PADDING;
+    const totalHeight = lines * lineHeight + 2 * PADDING;
     ctx.fillRect(0, 0, this.width, totalHeight);
     ctx.translate(PADDING, PADDING);
 
@@ -91,6 +93,9 @@ export class LeaderBoards {
         ctx.fillText(leaderboardsFormat(p), 0, 0);
         ctx.restore();
       });
+    if (matchLine) {
+      ctx.fillText(matchLine, 0, lineHeight * playersArr.length);
+    }
     ctx.restore();
   }
 }
@@ -124,3 +129,21 @@ function leaderboardsFormat(player: PlayerInfo) {
   // const deaths = player.deaths.toString().padEnd(DEATHS_CHARS, " ");
   return `${name} | ${ships} | ${kills} | ${islandPercent}`;
 }
+
+/** Round status, null when the round has no time limit and is still going */
+function matchFormat(info: MatchInfo, players: PlayerInfo[]) {
+  const round = `Round ${info.round}`;
+  if (!info.running) {
+    const winner = players.find((p) => p.id === info.winner);
+    if (!winner) {
+      return `${round} | ended`;
+    }
+    return `${round} | won by ${winner.name.slice(0, NAME_CHARS)}`;
+  }
+  if (info.remaining_time == null) {
+    return null;
+  }
+  const minutes = Math.floor(info.remaining_time / 60);
+  const seconds = Math.floor(info.remaining_time % 60);
+  return `${round} | ${minutes}:${seconds.toString().padStart(2, "0")} left`;
+}
@@ -212,6 +212,12 @@ export class MiniMap {
     return shape;
   }
 
+  rebuild() {
+    this.islandShapes = this.buildShapes();
+    this.smallIslandShapes = this.buildSmallShapes();
+    this.needUpdate = true;
+  }
+
   private updateIslands() {
     const ctx = this.islandsCanvas.getContext("2d")!;
     ctx.clearRect(0, 0, this.mapSizeInPixels, this.mapSizeInPixels);
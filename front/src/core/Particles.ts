@@ -44,6 +44,17 @@ export class ExplosionManager {
       velocity = 7;
       size = 0.7;
       timeToLive = 3;
+    } else if (data.kind === ExplosionKind.HeavyShot) {
+      velocity = 10;
+      size = 1;
+      timeToLive = 3;
+    } else if (
+      data.kind === ExplosionKind.ChainShot ||
+      data.kind === ExplosionKind.Grapeshot
+    ) {
+      velocity = 12;
+      size = 0.5;
+      timeToLive = 2;
     } else if (data.kind === ExplosionKind.Ship) {
       velocity = 30;
       size = 3;
@@ -1,4 +1,4 @@
-import { ExplosionKind } from "rust";
+import { AmmoKind, ExplosionKind } from "rust";
 
 export type V2D = { x: number; y: number };
 export type V3D = { x: number; y: number; z: number };
@@ -11,6 +11,12 @@ export type ShipData = {
   acceleration: V2D;
   orientation: V2D;
   hp: number;
+  kills: number;
+  damage_dealt: number;
+  rank: number;
+  merchant: boolean;
+  slow_time: number;
+  sinking: number | null;
 };
 
 export type ShipPosByPlayer = Float64Array;
@@ -20,6 +26,23 @@ export type Bullet = {
   speed: V3D;
   id: number;
   player_id: number;
+  ammo: AmmoKind;
+};
+
+export type InfluenceCell = {
+  friendly: number;
+  enemy: number;
+  island_value: number;
+  threat: number;
+};
+
+export type InfluenceMapData = {
+  player_id: number;
+  dim: number;
+  cell_size: number;
+  cells_dim: number;
+  cells: InfluenceCell[];
+  computed_at: number;
 };
 
 export type ExplosionData = {
@@ -45,6 +68,9 @@ export type IslandOwners = Map<
   {
     take_progress: number;
     owner?: number;
+    contesting: number[];
+    contested: boolean;
+    fort: FortData | null;
   }
 >;
 
@@ -57,9 +83,61 @@ export type PlayerInfo = {
   deaths: number;
   ships: number;
   islands: number;
+  team: number | null;
+  resources: number;
+  ship_cap: number;
 };
 
 export type CenterResults = {
   center: [number, number];
   count: number;
 };
+
+export type StormData = {
+  id: number;
+  position: V2D;
+  speed: V2D;
+  radius: number;
+  time_created: number;
+  ttl: number;
+};
+
+export type MatchInfo = {
+  round: number;
+  running: boolean;
+  remaining_time: number | null;
+  winner: number | null;
+};
+
+export type TeamStats = {
+  team: number;
+  players: number;
+  percentage_of_map: number;
+  islands: number;
+  ships: number;
+  kills: number;
+  deaths: number;
+};
+
+export type FortData = {
+  hp: number;
+  built: boolean;
+  last_shoot_time: number;
+};
+
+export type LootData = {
+  id: number;
+  position: V2D;
+  kind: "Wreck" | "Crate";
+  reward: "Repair" | "FastReload" | "Resources";
+  time_created: number;
+};
+
+export type TradeRouteData = {
+  id: number;
+  player_id: number;
+  from: number;
+  to: number;
+  path: V2D[];
+  last_spawn_time: number;
+};
@@ -22,6 +22,7 @@ const MAX_SHIPS = 120;
 const MAX_PLAYERS = 10;
 const ARMY_FLAG_HEIGHT = 50;
 const FRAMES_UNTIL_CHECK_DEAD_PLAYERS = 3_000;
+const SINK_DEPTH = 8;
 
 const up = new THREE.Vector3(0, 0, 1);
 const defaultColor = new THREE.Color(0x999999);
@@ -482,6 +483,7 @@ export class ShipsManager {
       new THREE.Matrix4().makeRotationFromQuaternion(quaternion),
       matrix
     );
-    matrix.setPosition(ship.position.x, ship.position.y, zPos);
+    const sinkDepth = (ship.sinking ?? 0) * SINK_DEPTH;
+    matrix.setPosition(ship.position.x, ship.position.y, zPos - sinkDepth);
   }
 }
@@ -0,0 +1,64 @@
+import { GameWasmState } from "rust";
+import * as THREE from "three";
+import { StormData } from "./RustWorldTypes";
+import { RenderOrder } from "./RenderOrder";
+
+const STORM_HEIGHT = 30;
+const STORM_OPACITY = 0.6;
+// Seconds a storm takes to fade in and out
+const FADE_TIME = 5;
+const STORM_COLOR = 0x3a3f4a;
+
+export class StormsManager {
+  private clouds = new Map<number, THREE.Mesh>();
+  private group = new THREE.Group();
+  private geometry = new THREE.CircleGeometry(1, 48);
+
+  constructor(readonly game: GameWasmState, readonly scene: THREE.Scene) {
+    this.scene.add(this.group);
+  }
+
+  tick(time: number) {
+    const storms: StormData[] = this.game.get_all_storms();
+    const alive = new Set<number>();
+    for (const storm of storms) {
+      alive.add(storm.id);
+      const cloud = this.clouds.get(storm.id) ?? this.makeCloud(storm.id);
+      cloud.position.set(storm.position.x, storm.position.y, STORM_HEIGHT);
+      cloud.scale.set(storm.radius, storm.radius, 1);
+      const age = time - storm.time_created;
+      const fade = Math.min(age, storm.ttl - age, FADE_TIME) / FADE_TIME;
+      const material = cloud.material as THREE.MeshBasicMaterial;
+      material.opacity = STORM_OPACITY * Math.max(0, fade);
+    }
+    for (const [id, cloud] of this.clouds) {
+      if (!alive.has(id)) {
+        this.group.remove(cloud);
+        (cloud.material as THREE.Material).dispose();
+        this.clouds.delete(id);
+      }
+    }
+  }
+
+  destroy() {
+    this.scene.remove(this.group);
+    this.clouds.forEach((cloud) =>
+      (cloud.material as THREE.Material).dispose()
+    );
+    this.geometry.dispose();
+  }
+
+  private makeCloud(id: number) {
+    const material = new THREE.MeshBasicMaterial({
+      color: STORM_COLOR,
+      transparent: true,
+      opacity: 0,
+      depthWrite: false,
+    });
+    const cloud = new THREE.Mesh(this.geometry, material);
+    cloud.renderOrder = RenderOrder.PARTICLES;
+    this.clouds.set(id, cloud);
+    this.group.add(cloud);
+    return cloud;
+  }
+}
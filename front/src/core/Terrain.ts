@@ -9,7 +9,7 @@ const SEGMENTS_PER_KM = 50;
 export class Terrain {
   minimap;
   terrainGroup = new THREE.Group();
-  constructor(gameState: GameWasmState, chunks: TerrainChunk[]) {
+  constructor(gameState: GameWasmState, private chunks: TerrainChunk[]) {
     this.minimap = new MiniMap(gameState);
     this.terrainGroup.add(...chunks.map((c) => c.planeMesh));
   }
@@ -31,6 +31,12 @@ export class Terrain {
     return new Terrain(gameState, chunks);
   }
 
+  /** A new round generated another world, the chunks are reshaped in place */
+  rebuild() {
+    this.chunks.forEach((chunk) => chunk.rebuild());
+    this.minimap.rebuild();
+  }
+
   tick(camera: THREE.Camera) {
     this.minimap.updateCanvas(camera);
   }
@@ -93,6 +99,15 @@ class TerrainChunk {
     return chunk;
   }
 
+  rebuild() {
+    const material = this.planeMesh.material as THREE.MeshLambertMaterial;
+    material.map?.dispose();
+    // the height range changes with the world, so does the pallette
+    material.map = TerrainChunk.terrainPalletteTexture(this.gameState);
+    material.needsUpdate = true;
+    this.updateMesh();
+  }
+
   updateMesh() {
     const { geometry } = this.planeMesh;
     const posArr = geometry.attributes.position.array;
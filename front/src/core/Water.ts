@@ -235,6 +235,14 @@ export class Water {
     waterPlaneGeometry.attributes.position.needsUpdate = true;
   }
 
+  /** The ocean texture follows the coastline, it is generated again with the world */
+  updateOcean(game: GameWasmState) {
+    const heightTexture = Water.generateHeightTexture(game);
+    this.material.uniforms.height_texture.value.dispose();
+    this.material.uniforms.height_texture.value = heightTexture;
+    this.simpleMaterial.uniforms.height_texture.value = heightTexture;
+  }
+
   setSunPosition(sunPosition: THREE.Vector3) {
     this.material.uniforms.sunPosition.value = sunPosition.clone().normalize();
     this.simpleMaterial.uniforms.sunPosition.value = sunPosition
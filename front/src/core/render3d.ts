@@ -7,6 +7,7 @@ import { RenderPass } from "three/addons/postprocessing/RenderPass.js";
 import { ShaderPass } from "three/addons/postprocessing/ShaderPass.js";
 import { OutlinePass } from "three/addons/postprocessing/OutlinePass.js";
 import { ShipsManager } from "./ShipsManager";
+import { StormsManager } from "./StormsManager";
 import { Water } from "./Water";
 import { CameraControl } from "./CameraControl";
 import { Terrain } from "./Terrain";
@@ -56,6 +57,7 @@ export class Render3D {
   readonly PLANE_SEGMENTS;
   readonly water;
   readonly shipsManager;
+  readonly stormsManager;
   readonly leaderboards;
 
   readonly terrain;
@@ -82,6 +84,7 @@ export class Render3D {
       this.water,
       this.cameraControls
     );
+    this.stormsManager = new StormsManager(this.gameState, this.scene);
     this.terrain = Terrain.new(this.gameState);
     this.canvas = document.createElement("canvas");
     this.canvas.style.width = "100%";
@@ -151,6 +154,7 @@ export class Render3D {
     this.renderer?.dispose();
     [...this.canvas.parentElement!.children].forEach((el) => el.remove());
     this.shipsManager.destroy();
+    this.stormsManager.destroy();
     this.cameraControls.destroy();
     this.playerActions.destroy();
     this.scene.traverse((obj: any) => {
@@ -294,7 +298,12 @@ export class Render3D {
         this.gameState.tick(gameTime);
       }
       const gameTime = this.gameState.current_time;
+      if (this.gameState.has_world_changed()) {
+        this.terrain.rebuild();
+        this.water.updateOcean(this.gameState);
+      }
       this.shipsManager.tick(gameTime, frames);
+      this.stormsManager.tick(gameTime);
       this.water.tick(gameTime, this.camera);
       this.terrain.tick(this.camera);
       this.playerActions.tick();
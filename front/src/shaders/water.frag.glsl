@@ -13,23 +13,42 @@ uniform float z_gain;
 const vec3 SHALLOW_COLOR = vec3(0.0, 0.9, 1.0);
 const float FOAM_TEXTURE_SCALE = 0.003;
 const vec3 WHITE = vec3(1.0, 1.0, 1.0);
+const float FLOW_STRENGTH = 0.05;
 
 varying vec3 normal_v;
 varying vec3 vViewPosition;
 varying vec2 vUv;
 varying float depth;
 
+// ocean current stored in the blue and alpha channels, 0.5 means no current
+vec2 get_flow() {
+  return texture2D(height_texture, vUv).ba * 2.0 - 1.0;
+}
+
 vec3 get_displacement() {
   vec2 uv = vViewPosition.xy / texture_scale;
   float t = time / 10.0;
+  // two phases of the flow so the texture never stretches too much
+  vec2 flow = get_flow() * FLOW_STRENGTH;
+  float phase1 = fract(t);
+  float phase2 = fract(t + 0.5);
+  float flow_mix = abs((0.5 - phase1) / 0.5);
   vec2 uv_offset1 = uv + vec2(t / 17.0, t / 29.0);
   vec2 uv_offset2 = uv + vec2(-t / 19.0, t / 32.0);
   // vec2 uv_offset3 = uv + vec2(t / 101.0, -t / 113.0);
   // vec2 uv_offset4 = uv + vec2(-t / 109.0, t / 97.0);
   // vec4 normal3 = texture2D(normal_map, uv_offset3);
   // vec4 normal4 = texture2D(normal_map, uv_offset4);
-  vec4 normal1 = texture2D(normal_map, uv_offset1);
-  vec4 normal2 = texture2D(normal_map, uv_offset2);
+  vec4 normal1 = mix(
+    texture2D(normal_map, uv_offset1 - flow * phase1),
+    texture2D(normal_map, uv_offset1 - flow * phase2),
+    flow_mix
+  );
+  vec4 normal2 = mix(
+    texture2D(normal_map, uv_offset2 - flow * phase1),
+    texture2D(normal_map, uv_offset2 - flow * phase2),
+    flow_mix
+  );
   vec3 normal = normal1.xyz + normal2.xyz;
   normal.z = normal.z * z_gain;
   return normalize(normal);
@@ -0,0 +1,208 @@
+//! Plays bot only matches as fast as possible and prints how each kind of bot did.
+//!
+//! cargo run --release --bin tournament -- --seeds 1,2,3 --bots Hard:Aggressive,Normal:Balanced:Rush
+//!
+//! Each bot is difficulty[:personality[:brain]], the same bot can show up more than once.
+
+use game_state::{BotConfig, GameServer, MatchRules, PlayerState, ServerConfig, TICK_TIME};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+
+const DEFAULT_TIME_LIMIT: f64 = 600.0;
+const DEFAULT_MAP_CONTROL: f64 = 50.0;
+/// Extra seconds after the time limit before we give up on the match
+const TICK_LIMIT_MARGIN: f64 = 60.0;
+
+struct TournamentConfig {
+    seeds: Vec<u32>,
+    bots: Vec<BotConfig>,
+    time_limit: f64,
+    map_control: f64,
+}
+
+struct MatchOutcome {
+    winner: Option<BotConfig>,
+    /// Every life of every bot, with the last stats we saw for it
+    lives: Vec<(BotConfig, PlayerState)>,
+    /// Map percentage of each bot when the match ended
+    final_map: Vec<(BotConfig, f64)>,
+    seconds: f64,
+}
+
+#[derive(Default)]
+struct BotReport {
+    games: usize,
+    wins: usize,
+    map_percentage: f64,
+    kills: usize,
+    deaths: usize,
+}
+
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_str(&format!("\"{value}\"")).map_err(|_| format!("unknown value {value}"))
+}
+
+fn parse_bot(value: &str) -> Result<BotConfig, String> {
+    let mut config = BotConfig::default();
+    let mut parts = value.split(':');
+    if let Some(difficulty) = parts.next() {
+        config.difficulty = parse_enum(difficulty)?;
+    }
+    if let Some(personality) = parts.next() {
+        config.personality = parse_enum(personality)?;
+    }
+    if let Some(brain) = parts.next() {
+        config.brain = parse_enum(brain)?;
+    }
+    Ok(config)
+}
+
+fn parse_args() -> Result<TournamentConfig, String> {
+    let mut config = TournamentConfig {
+        seeds: vec![1, 2, 3],
+        bots: vec![BotConfig::default(); 2],
+        time_limit: DEFAULT_TIME_LIMIT,
+        map_control: DEFAULT_MAP_CONTROL,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("missing value for {flag}"))?;
+        match flag.as_str() {
+            "--seeds" => {
+                config.seeds = value
+                    .split(',')
+                    .map(|seed| seed.parse().map_err(|_| format!("bad seed {seed}")))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--bots" => {
+                config.bots = value.split(',').map(parse_bot).collect::<Result<_, _>>()?;
+            }
+            "--time-limit" => {
+                config.time_limit = value.parse().map_err(|_| "bad time limit")?;
+            }
+            "--map-control" => {
+                config.map_control = value.parse().map_err(|_| "bad map control")?;
+            }
+            _ => return Err(format!("unknown flag {flag}")),
+        }
+    }
+    Ok(config)
+}
+
+fn play_match(config: &TournamentConfig, seed: u32) -> MatchOutcome {
+    let mut server = GameServer::new(None, seed);
+    server.headless = true;
+    server.set_config(ServerConfig {
+        match_rules: MatchRules {
+            time_limit: Some(config.time_limit),
+            map_control_to_win: Some(config.map_control),
+            last_player_standing: true,
+        },
+        ..Default::default()
+    });
+    for bot in config.bots.iter() {
+        server.add_bot(*bot);
+    }
+
+    // bots get a new id when they respawn, so we remember every id we saw
+    let mut bot_ids: BTreeMap<u64, BotConfig> = BTreeMap::new();
+    let mut last_seen: BTreeMap<u64, PlayerState> = BTreeMap::new();
+    let max_ticks = ((config.time_limit + TICK_LIMIT_MARGIN) / TICK_TIME) as u64;
+    let mut winner = None;
+    for _ in 0..max_ticks {
+        server.tick(TICK_TIME);
+        bot_ids.extend(server.bot_configs());
+        for player in server.game_state.players.values() {
+            last_seen.insert(player.id, player.clone());
+        }
+        let info = server
+            .game_state
+            .match_state
+            .info(server.game_state.current_time);
+        if !info.running {
+            winner = info.winner;
+            break;
+        }
+    }
+
+    let players = &server.game_state.players;
+    MatchOutcome {
+        winner: winner.and_then(|id| bot_ids.get(&id).copied()),
+        lives: last_seen
+            .into_iter()
+            .filter_map(|(id, stats)| Some((*bot_ids.get(&id)?, stats)))
+            .collect(),
+        final_map: server
+            .bot_configs()
+            .into_iter()
+            .map(|(id, config)| {
+                let map = players
+                    .get(&id)
+                    .map_or(0.0, |player| player.percentage_of_map);
+                (config, map)
+            })
+            .collect(),
+        seconds: server.game_state.current_time,
+    }
+}
+
+fn bot_name(config: &BotConfig) -> String {
+    format!(
+        "{:?}:{:?}:{:?}",
+        config.difficulty, config.personality, config.brain
+    )
+}
+
+fn main() {
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("usage: tournament [--seeds 1,2] [--bots Hard:Aggressive:Standard,Easy] [--time-limit 600] [--map-control 50]");
+            std::process::exit(1);
+        }
+    };
+
+    let mut reports: BTreeMap<String, BotReport> = BTreeMap::new();
+    for bot in config.bots.iter() {
+        reports.entry(bot_name(bot)).or_default().games += config.seeds.len();
+    }
+    for &seed in config.seeds.iter() {
+        let outcome = play_match(&config, seed);
+        let winner = outcome.winner.as_ref().map(bot_name);
+        println!(
+            "seed {seed}: {} after {:.0}s",
+            winner.as_deref().unwrap_or("no winner"),
+            outcome.seconds
+        );
+        if let Some(winner) = winner {
+            reports.entry(winner).or_default().wins += 1;
+        }
+        for (bot, map) in outcome.final_map {
+            reports.entry(bot_name(&bot)).or_default().map_percentage += map;
+        }
+        for (bot, stats) in outcome.lives {
+            let report = reports.entry(bot_name(&bot)).or_default();
+            report.kills += stats.kills;
+            report.deaths += stats.deaths;
+        }
+    }
+
+    println!();
+    println!(
+        "{:<32} {:>6} {:>8} {:>8} {:>8} {:>8}",
+        "bot", "games", "win %", "map %", "kills", "deaths"
+    );
+    for (name, report) in reports.iter() {
+        let games = report.games.max(1) as f64;
+        println!(
+            "{:<32} {:>6} {:>8.1} {:>8.1} {:>8} {:>8}",
+            name,
+            report.games,
+            report.wins as f64 / games * 100.0,
+            report.map_percentage / games,
+            report.kills,
+            report.deaths
+        );
+    }
+}
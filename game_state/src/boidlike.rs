@@ -1,6 +1,11 @@
 use cgmath::{InnerSpace, MetricSpace};
+use std::collections::BTreeMap;
 
-use crate::utils::vectors::V2D;
+use crate::{
+    hashgrid::{HashEntity, HashEntityKind, HashGrid},
+    ship::ShipKey,
+    utils::vectors::V2D,
+};
 
 const NEAR: f64 = 40.0;
 const TOO_CLOSE: f64 = 20.0;
@@ -8,7 +13,11 @@ const TOO_CLOSE: f64 = 20.0;
 pub trait BoidLike: PartialEq + Clone {
     fn position(&self) -> V2D;
     fn velocity(&self) -> V2D;
-    fn update(&self, speed: V2D) -> Self;
+    /// Only flocking boids get steered, the others still push the flocking ones away
+    fn is_flocking(&self) -> bool;
+    fn flocks_with(&self, other: &Self) -> bool;
+    /// Applies the steering direction, a unit vector or zero
+    fn steer(&mut self, direction: V2D);
 
     fn is_near(&self, other: &Self) -> bool {
         let distance = self.position().distance(other.position());
@@ -20,73 +29,140 @@ pub trait BoidLike: PartialEq + Clone {
     }
 }
 
-pub struct BoidsTeam<B: BoidLike> {
-    pub boids: Vec<B>,
+pub struct BoidsTeam<'a, B: BoidLike> {
+    pub boids: &'a mut BTreeMap<ShipKey, B>,
 }
 
-impl<B: BoidLike> BoidsTeam<B> {
-    pub fn update_boids_like(boids: Vec<B>) -> Vec<B> {
-        let mut team = BoidsTeam::new(boids);
-        team.update();
-        team.boids
-    }
-
-    fn new(boids: Vec<B>) -> Self {
+impl<'a, B: BoidLike> BoidsTeam<'a, B> {
+    pub fn new(boids: &'a mut BTreeMap<ShipKey, B>) -> Self {
         Self { boids }
     }
 
-    pub fn update(&mut self) {
-        let mut rand_gen = fastrand::Rng::with_seed(0);
-        self.boids = self
+    /// Steers every flocking boid away from the ones too close and along with the ones near,
+    /// the others get a zero steering.
+    /// Neighbours come from a grid with cells of the NEAR size, so a dense fleet only looks
+    /// at the few boids around each one. The state's hash grid has cells as big as the
+    /// bullet range, which would return almost the whole fleet.
+    /// The rng only breaks ties between boids on the same spot, seed it with the frame
+    /// so every client steers the same way.
+    pub fn update(&mut self, map_dim: f64, seed: u64) {
+        let mut rand_gen = fastrand::Rng::with_seed(seed);
+        let mut hash_grid = HashGrid::new(map_dim, NEAR);
+        for (key, boid) in self.boids.iter() {
+            hash_grid.insert(HashEntity {
+                position: boid.position(),
+                entity: HashEntityKind::Boat(*key),
+            });
+        }
+        let steering: Vec<_> = self
             .boids
             .iter()
-            .map(|boid| {
-                let mut repulsion = V2D::new(0.0, 0.0);
-                let mut sum_positions = V2D::new(0.0, 0.0);
-                let mut total_positions = 0;
-                let mut alignment_speed = V2D::new(0.0, 0.0);
-                self.boids.iter().for_each(|other| {
-                    if !boid.is_near(other) {
-                        return;
-                    }
-                    if boid.is_too_close(other) {
-                        let d = (boid.position() - other.position()).normalize();
-                        if d.x.is_nan() || d.y.is_nan() {
-                            repulsion += V2D::new(rand_gen.f64(), rand_gen.f64()).normalize();
-                        } else {
-                            repulsion += d;
-                        }
-                    } else {
-                        alignment_speed += other.velocity().normalize();
-                    }
-                    sum_positions += other.position();
-                    total_positions += 1;
-                });
-                let average_position = if total_positions > 0 {
-                    sum_positions / total_positions as f64
-                } else {
-                    V2D::new(0.0, 0.0)
-                };
-                let cohesion_speed = average_position - boid.position();
-
-                let result = safe_normalize(repulsion) * 10.0
-                    + safe_normalize(alignment_speed) * 1.0
-                    + safe_normalize(cohesion_speed) * 1.0;
-
-                if repulsion.magnitude() > 0.0 {
-                    boid.update(safe_normalize(result))
-                } else {
-                    boid.clone()
+            .map(|(key, boid)| {
+                if !boid.is_flocking() {
+                    return (*key, V2D::new(0.0, 0.0));
                 }
+                let neighbours = hash_grid
+                    .query_near(boid.position(), NEAR)
+                    .filter_map(|entity| entity.as_boat())
+                    .filter_map(|(key, _)| self.boids.get(&key))
+                    .filter(|other| boid.flocks_with(other));
+                (*key, steering(boid, neighbours, &mut rand_gen))
             })
             .collect();
+        for (key, direction) in steering {
+            if let Some(boid) = self.boids.get_mut(&key) {
+                boid.steer(direction);
+            }
+        }
     }
 }
 
+/// Separation, alignment and cohesion. Zero while no neighbour is too close.
+fn steering<'a, B: BoidLike + 'a>(
+    boid: &B,
+    neighbours: impl Iterator<Item = &'a B>,
+    rand_gen: &mut fastrand::Rng,
+) -> V2D {
+    let mut repulsion = V2D::new(0.0, 0.0);
+    let mut sum_positions = V2D::new(0.0, 0.0);
+    let mut total_positions = 0;
+    let mut alignment_speed = V2D::new(0.0, 0.0);
+    neighbours.for_each(|other| {
+        if !boid.is_near(other) {
+            return;
+        }
+        if boid.is_too_close(other) {
+            let d = (boid.position() - other.position()).normalize();
+            if d.x.is_nan() || d.y.is_nan() {
+                repulsion += V2D::new(rand_gen.f64() - 0.5, rand_gen.f64() - 0.5).normalize();
+            } else {
+                repulsion += d;
+            }
+        } else {
+            alignment_speed += safe_normalize(other.velocity());
+        }
+        sum_positions += other.position();
+        total_positions += 1;
+    });
+    if repulsion.magnitude() == 0.0 {
+        return V2D::new(0.0, 0.0);
+    }
+    let average_position = sum_positions / total_positions as f64;
+    let cohesion_speed = average_position - boid.position();
+
+    let result = safe_normalize(repulsion) * 10.0
+        + safe_normalize(alignment_speed) * 1.0
+        + safe_normalize(cohesion_speed) * 1.0;
+    safe_normalize(result)
+}
+
 fn safe_normalize(v: V2D) -> V2D {
+    let v = v.normalize();
     if v.x.is_nan() || v.y.is_nan() {
         V2D::new(0.0, 0.0)
     } else {
-        v.normalize()
+        v
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BoidsTeam;
+    use crate::ship::ShipState;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_flocking_keeps_ships_apart() {
+        let mut ships = BTreeMap::new();
+        let moving = |id, player_id, x| ShipState {
+            id,
+            player_id,
+            position: (x, 0.0).into(),
+            target_speed: (0.0, 16.0).into(),
+            ..Default::default()
+        };
+        let fleet = [
+            moving(1, 1, 0.0),
+            moving(2, 1, 10.0),
+            moving(3, 2, -10.0),
+            moving(4, 1, 300.0),
+        ];
+        for ship in fleet {
+            ships.insert(ship.key(), ship);
+        }
+
+        BoidsTeam::new(&mut ships).update(1000.0, 0);
+        let flock_speed = |id, player_id| {
+            ships
+                .values()
+                .find(|ship| ship.id == id && ship.player_id == player_id)
+                .unwrap()
+                .flock_speed
+        };
+        assert!(flock_speed(1, 1).x < 0.0);
+        assert!(flock_speed(2, 1).x > 0.0);
+        // other players' ships and ships far away don't push
+        assert_eq!(flock_speed(3, 2).x, 0.0);
+        assert_eq!(flock_speed(4, 1).x, 0.0);
     }
 }
@@ -0,0 +1,109 @@
+use cgmath::MetricSpace;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bot_player::{BotConfig, StandardBrain},
+    bullet::AmmoKind,
+    player::Player,
+    server_state::ServerState,
+    utils::vectors::V2D,
+};
+
+/// Seconds between two attacks of the rush brain
+const RUSH_INTERVAL: f64 = 5.0;
+
+/// What a brain wants its bot to do, the bot turns them into StateMessages through its Player
+#[derive(Debug, Clone, PartialEq)]
+pub enum BotOrder {
+    /// Sends the ships along a path to the target
+    Move {
+        ships: Vec<u64>,
+        target: V2D,
+    },
+    Shoot {
+        ship_id: u64,
+        target: V2D,
+    },
+    /// Every ship picks its own target among the enemies in range
+    AutoShoot,
+    SetAmmo(AmmoKind),
+    CreateTradeRoute {
+        from: u64,
+        to: u64,
+    },
+    BuildFort {
+        island_id: u64,
+    },
+}
+
+/// Decision making of a bot. It only reads the game, every change goes through the orders it returns,
+/// so different implementations can play side by side on the same server.
+pub trait BotBrain: Send {
+    /// Called every tick with the bot's Player. Its idle ships are the ones
+    /// without a path in game_state.ship_paths
+    fn think(&mut self, game_state: &ServerState, player: &Player) -> Vec<BotOrder>;
+
+    /// Called with the id of the bot's player when it joins the server,
+    /// and again each time it starts over as a new player
+    fn start(&mut self, _player_id: u64) {}
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum BotBrainKind {
+    #[default]
+    Standard,
+    /// Throws every ship at the closest island that is not ours
+    Rush,
+}
+
+impl BotBrainKind {
+    pub fn create(&self, config: BotConfig) -> Box<dyn BotBrain> {
+        match self {
+            BotBrainKind::Standard => Box::new(StandardBrain::new(config, 0)),
+            BotBrainKind::Rush => Box::new(RushBrain::default()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RushBrain {
+    next_attack: f64,
+}
+
+impl BotBrain for RushBrain {
+    fn think(&mut self, game_state: &ServerState, player: &Player) -> Vec<BotOrder> {
+        let mut orders = vec![BotOrder::AutoShoot];
+        if game_state.current_time < self.next_attack {
+            return orders;
+        }
+        self.next_attack = game_state.current_time + RUSH_INTERVAL;
+        let ships = player.idle_ships(game_state);
+        let first = match player
+            .player_ships(game_state)
+            .find(|ship| ships.contains(&ship.id))
+        {
+            Some(ship) => ship.position,
+            None => return orders,
+        };
+        let target = game_state
+            .island_dynamic
+            .values()
+            .filter(|island| {
+                !island
+                    .owner
+                    .is_some_and(|owner| game_state.are_allies(owner, player.id))
+            })
+            .map(|island| island.lighthouse)
+            .min_by(|a, b| a.distance(first).total_cmp(&b.distance(first)));
+        if let Some(target) = target {
+            orders.push(BotOrder::Move { ships, target });
+        }
+        orders
+    }
+
+    fn start(&mut self, _player_id: u64) {
+        *self = Self::default();
+    }
+}
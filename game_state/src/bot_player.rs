@@ -1,49 +1,258 @@
 use cgmath::MetricSpace;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
 
-use crate::{island::Island, player::Player, server_state::ServerState, utils::vectors::V2D};
+use crate::{
+    bot_brain::{BotBrain, BotBrainKind, BotOrder},
+    influence::InfluenceMap,
+    island::Island,
+    player::Player,
+    server_state::ServerState,
+    ship::{ShipKey, SHIP_MAX_HP},
+    utils::vectors::V2D,
+};
 
 enum BotState {
     WaitingShips,
     Conquering(Island),
     Reiforcing(Island),
+    /// One of our islands is being taken, defenders were sent to it
+    Defending(Island),
     Dead,
 }
 
 const UNITS_PER_ISLAND_TO_ATTACK_AGAIN: usize = 20;
 const TIME_FOR_ACTION: f64 = 1.0;
+/// Ships this close to a position count for the fleet strength there
+const THREAT_RADIUS: f64 = 150.0;
+/// Island tiles that make an island worth twice the distance when choosing a target
+const ISLAND_VALUE_SCALE: f64 = 200.0;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum BotPersonality {
+    #[default]
+    Balanced,
+    /// Attacks with smaller fleets and goes after enemy islands first
+    Aggressive,
+    /// Prefers grabbing free islands
+    Expansionist,
+    /// Waits for bigger fleets and keeps part of them home
+    Defensive,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct BotConfig {
+    pub difficulty: BotDifficulty,
+    pub personality: BotPersonality,
+    pub brain: BotBrainKind,
+}
+
+/// Numbers that drive the bot decisions, derived from its BotConfig
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BotParams {
+    time_for_action: f64,
+    /// Chance of doing something each time the bot gets to act
+    action_chance: f64,
+    /// Radius of the error the bot adds to its shots
+    shoot_radius: f64,
+    units_per_island_to_attack: usize,
+    /// Fraction of the idle ships left behind when attacking
+    garrison: f64,
+    /// Multiplies the distance to free islands when choosing a target
+    free_island_factor: f64,
+    /// Multiplies the distance to enemy islands when choosing a target
+    enemy_island_factor: f64,
+    /// Ships sent to an island under attack for each enemy ship around it
+    defenders_per_enemy: f64,
+    /// The bot retreats when the enemy is this many times stronger,
+    /// and only attacks when it is this many times stronger than the enemy
+    retreat_ratio: f64,
+}
+
+impl BotConfig {
+    fn params(&self) -> BotParams {
+        let (time_for_action, action_chance, shoot_radius) = match self.difficulty {
+            BotDifficulty::Easy => (TIME_FOR_ACTION * 2.0, 0.3, 20.0),
+            BotDifficulty::Normal => (TIME_FOR_ACTION, 0.5, 10.0),
+            BotDifficulty::Hard => (TIME_FOR_ACTION / 2.0, 0.8, 5.0),
+        };
+        let params = BotParams {
+            time_for_action,
+            action_chance,
+            shoot_radius,
+            units_per_island_to_attack: UNITS_PER_ISLAND_TO_ATTACK_AGAIN,
+            garrison: 0.0,
+            free_island_factor: 1.0,
+            enemy_island_factor: 1.0,
+            defenders_per_enemy: 1.5,
+            retreat_ratio: 1.5,
+        };
+        match self.personality {
+            BotPersonality::Balanced => params,
+            BotPersonality::Aggressive => BotParams {
+                units_per_island_to_attack: UNITS_PER_ISLAND_TO_ATTACK_AGAIN / 2,
+                enemy_island_factor: 0.5,
+                defenders_per_enemy: 1.0,
+                retreat_ratio: 2.0,
+                ..params
+            },
+            BotPersonality::Expansionist => BotParams {
+                units_per_island_to_attack: UNITS_PER_ISLAND_TO_ATTACK_AGAIN * 3 / 4,
+                free_island_factor: 0.5,
+                enemy_island_factor: 1.5,
+                ..params
+            },
+            BotPersonality::Defensive => BotParams {
+                units_per_island_to_attack: UNITS_PER_ISLAND_TO_ATTACK_AGAIN * 3 / 2,
+                garrison: 0.3,
+                defenders_per_enemy: 2.0,
+                retreat_ratio: 1.2,
+                ..params
+            },
+        }
+    }
+}
 
 pub struct BotPlayer {
     pub player: Player,
-    bot_state: BotState,
-    time_to_next_action: f64,
+    pub config: BotConfig,
+    brain: Box<dyn BotBrain>,
+    /// Set once the bot's first ships show up, so it is not taken for dead before that
+    had_ships: bool,
+    ships: usize,
 }
 
 impl BotPlayer {
-    pub fn new(id: u64) -> Self {
+    pub fn new(id: u64, config: BotConfig, brain: Box<dyn BotBrain>) -> Self {
+        let mut player = Player::new(id);
+        player.avoid_storms = true;
+        player.shoot_radius = config.params().shoot_radius;
         Self {
-            player: Player::new(id),
-            bot_state: BotState::WaitingShips,
-            time_to_next_action: 0.0,
+            player,
+            config,
+            brain,
+            had_ships: false,
+            ships: 0,
         }
     }
 
     pub fn is_dead(&self) -> bool {
-        matches!(self.bot_state, BotState::Dead)
+        self.had_ships && self.ships == 0
+    }
+
+    /// Gives the brain back so the bot can start over with it
+    pub fn into_brain(self) -> Box<dyn BotBrain> {
+        self.brain
+    }
+
+    pub fn tick(&mut self, _dt: f64, game_state: &ServerState) {
+        for order in self.brain.think(game_state, &self.player) {
+            self.apply(game_state, order);
+        }
+        self.ships = self.player.number_of_ships(game_state);
+        self.had_ships |= self.ships > 0;
+    }
+
+    fn apply(&mut self, game_state: &ServerState, order: BotOrder) {
+        match order {
+            BotOrder::Move { ships, target } => {
+                self.player.selected_ships = ships;
+                self.player
+                    .move_selected_ships(game_state, target.x, target.y);
+            }
+            BotOrder::Shoot { ship_id, target } => {
+                self.player.shoot_at_with(ship_id, target.x, target.y);
+            }
+            BotOrder::AutoShoot => {
+                self.player.select_all(game_state);
+                self.player.auto_shoot(game_state);
+            }
+            BotOrder::SetAmmo(ammo) => self.player.ammo = ammo,
+            BotOrder::CreateTradeRoute { from, to } => self.player.create_trade_route(from, to),
+            BotOrder::BuildFort { island_id } => self.player.build_fort(island_id),
+        }
+    }
+}
+
+/// The bot we ship: grabs the closest islands it can beat, reinforces and defends them
+pub struct StandardBrain {
+    player_id: u64,
+    config: BotConfig,
+    params: BotParams,
+    bot_state: BotState,
+    time_to_next_action: f64,
+    /// take_progress of our islands the last time the bot looked at them
+    take_progress: BTreeMap<u64, f64>,
+    influence: Option<InfluenceMap>,
+    rng: fastrand::Rng,
+    orders: Vec<BotOrder>,
+}
+
+impl BotBrain for StandardBrain {
+    fn think(&mut self, game_state: &ServerState, player: &Player) -> Vec<BotOrder> {
+        self.orders.push(BotOrder::AutoShoot);
+        self.decide(game_state, player);
+        std::mem::take(&mut self.orders)
+    }
+
+    fn start(&mut self, player_id: u64) {
+        *self = Self::new(self.config, player_id);
+    }
+}
+
+impl StandardBrain {
+    pub fn new(config: BotConfig, player_id: u64) -> Self {
+        Self {
+            player_id,
+            config,
+            params: config.params(),
+            bot_state: BotState::WaitingShips,
+            time_to_next_action: 0.0,
+            take_progress: BTreeMap::new(),
+            influence: None,
+            rng: fastrand::Rng::with_seed(player_id),
+            orders: vec![],
+        }
     }
 
-    pub fn tick(&mut self, _dt: f64, game_state: &ServerState) -> Option<()> {
-        self.player.tick(&game_state);
-        self.player.select_all(&game_state);
-        self.player.auto_shoot(&game_state);
-        let ships_number = self.player.number_of_ships(&game_state);
-        let idle_ships = self.player.number_of_idle_ships(game_state);
+    fn decide(&mut self, game_state: &ServerState, player: &Player) -> Option<()> {
+        let ships_number = player.number_of_ships(game_state);
+        let idle_ships = player.number_of_idle_ships(game_state);
 
         let current_time = game_state.current_time;
         if current_time < self.time_to_next_action {
             return None;
         }
-        self.time_to_next_action = current_time + TIME_FOR_ACTION;
-        let should_take_action = self.player.rng.f64() < 0.5;
+        self.time_to_next_action = current_time + self.params.time_for_action;
+        if matches!(self.bot_state, BotState::Dead) {
+            return None;
+        }
+        let is_outdated = self
+            .influence
+            .as_ref()
+            .is_none_or(|map| map.needs_update(current_time));
+        if is_outdated {
+            self.influence = Some(InfluenceMap::compute(game_state, self.player_id));
+        }
+        if let Some(island) = self.island_under_attack(game_state) {
+            if ships_number > 0 {
+                self.defend_island(game_state, player, &island);
+                self.bot_state = BotState::Defending(island);
+            }
+            return None;
+        }
+        let should_take_action = self.rng.f64() < self.params.action_chance;
         if !should_take_action {
             return None;
         }
@@ -51,9 +260,11 @@ impl BotPlayer {
         match &self.bot_state {
             BotState::WaitingShips => {
                 if ships_number > 0 {
-                    let closest_island = self.closes_island_not_mine(game_state)?;
-                    self.attack_island(game_state, &closest_island);
-                    self.bot_state = BotState::Conquering(closest_island);
+                    let closest_island = self.closes_island_not_mine(game_state, player)?;
+                    if self.can_engage(game_state, player, &closest_island) {
+                        self.attack_island(game_state, player, &closest_island);
+                        self.bot_state = BotState::Conquering(closest_island);
+                    }
                 }
             }
             BotState::Conquering(island) => {
@@ -63,13 +274,20 @@ impl BotPlayer {
                 }
                 let island = island.clone();
                 let units_to_attack =
-                    self.my_islands(game_state) * UNITS_PER_ISLAND_TO_ATTACK_AGAIN / 2;
+                    self.my_islands(game_state) * self.params.units_per_island_to_attack / 2;
                 if let Some(island_dyn) = game_state.island_dynamic.get(&island.id) {
-                    let is_mine = island_dyn.owner == Some(self.player.id);
+                    let is_mine = island_dyn.owner == Some(self.player_id);
                     if is_mine {
                         self.bot_state = BotState::Reiforcing(island.clone());
-                    } else if idle_ships >= units_to_attack {
-                        self.attack_island(game_state, &island);
+                    } else if self.is_losing_at(game_state, island.light_house) {
+                        if let Some(safe) = self.closest_friendly_island(game_state, &island) {
+                            self.retreat(game_state, player, island.light_house, &safe);
+                            self.bot_state = BotState::Reiforcing(safe);
+                        }
+                    } else if idle_ships >= units_to_attack
+                        && self.can_engage(game_state, player, &island)
+                    {
+                        self.attack_island(game_state, player, &island);
                     }
                 }
             }
@@ -78,13 +296,30 @@ impl BotPlayer {
                     self.bot_state = BotState::Dead;
                     return None;
                 }
+                self.setup_trade_route(game_state);
                 let units_to_attack =
-                    self.my_islands(game_state) * UNITS_PER_ISLAND_TO_ATTACK_AGAIN;
+                    self.my_islands(game_state) * self.params.units_per_island_to_attack;
 
                 if idle_ships >= units_to_attack {
-                    let closest_island = self.closes_island_not_mine(game_state)?;
-                    self.attack_island(game_state, &closest_island);
-                    self.bot_state = BotState::Conquering(closest_island);
+                    let closest_island = self.closes_island_not_mine(game_state, player)?;
+                    if self.can_engage(game_state, player, &closest_island) {
+                        self.attack_island(game_state, player, &closest_island);
+                        self.bot_state = BotState::Conquering(closest_island);
+                    }
+                }
+            }
+            BotState::Defending(island) => {
+                if ships_number == 0 {
+                    self.bot_state = BotState::Dead;
+                    return None;
+                }
+                let island = island.clone();
+                let island_dyn = game_state.island_dynamic.get(&island.id)?;
+                if island_dyn.owner != Some(self.player_id) {
+                    // we lost it, take it back
+                    self.bot_state = BotState::Conquering(island);
+                } else if !island_dyn.contested {
+                    self.bot_state = BotState::Reiforcing(island);
                 }
             }
             BotState::Dead => {
@@ -94,23 +329,187 @@ impl BotPlayer {
         return None;
     }
 
-    fn attack_island(&mut self, game_state: &ServerState, island: &Island) {
-        self.player.select_all_idle(game_state);
-        self.player
-            .move_selected_ships(game_state, island.light_house.x, island.light_house.y);
+    /// Idle ships attack_island sends, the garrison stays home
+    fn attackers(&self, game_state: &ServerState, player: &Player) -> Vec<u64> {
+        let mut ships = player.idle_ships(game_state);
+        let garrison = (ships.len() as f64 * self.params.garrison) as usize;
+        ships.truncate(ships.len() - garrison);
+        ships
+    }
+
+    fn attack_island(&mut self, game_state: &ServerState, player: &Player, island: &Island) {
+        let ships = self.attackers(game_state, player);
+        self.orders.push(BotOrder::Move {
+            ships,
+            target: island.light_house,
+        });
+    }
+
+    /// Hp of the armed ships around the position, ours and our allies' first, then the enemies'.
+    /// A ship at full hp counts as 1. Ships hidden by storms we can't see into are left out.
+    fn fleet_strengths(&self, game_state: &ServerState, position: V2D) -> (f64, f64) {
+        let mut friendly = 0.0;
+        let mut enemy = 0.0;
+        let seen_storms = game_state.storms_seen_by(self.player_id);
+        game_state
+            .hash_grid
+            .query_near(position, THREAT_RADIUS)
+            .filter_map(|entity| entity.as_boat())
+            .filter(|(_, position)| !game_state.is_hidden_by_storm(*position, &seen_storms))
+            .filter_map(|(key, _)| game_state.ship_collection.get(&key))
+            .filter(|ship| !ship.is_merchant())
+            .for_each(|ship| {
+                let strength = ship.hp / SHIP_MAX_HP;
+                if game_state.are_allies(self.player_id, ship.player_id) {
+                    friendly += strength;
+                } else {
+                    enemy += strength;
+                }
+            });
+        (friendly, enemy)
+    }
+
+    /// True when the ships attack_island would send plus whatever we have there beat the defenders by retreat_ratio.
+    /// Attackers already close to the island are counted by fleet_strengths.
+    fn can_engage(&self, game_state: &ServerState, player: &Player, island: &Island) -> bool {
+        let target = island.light_house;
+        let (friendly, enemy) = self.fleet_strengths(game_state, target);
+        let player_id = self.player_id;
+        let sent: f64 = self
+            .attackers(game_state, player)
+            .into_iter()
+            .filter_map(|id| game_state.ship_collection.get(&ShipKey { id, player_id }))
+            .filter(|ship| ship.position.distance(target) >= THREAT_RADIUS)
+            .map(|ship| ship.hp / SHIP_MAX_HP)
+            .sum();
+        friendly + sent > enemy * self.params.retreat_ratio
+    }
+
+    fn is_losing_at(&self, game_state: &ServerState, position: V2D) -> bool {
+        let (friendly, enemy) = self.fleet_strengths(game_state, position);
+        friendly > 0.0 && enemy > friendly * self.params.retreat_ratio
+    }
+
+    /// First of our islands whose take_progress went down since the last look
+    fn island_under_attack(&mut self, game_state: &ServerState) -> Option<Island> {
+        let mut attacked = None;
+        self.take_progress
+            .retain(|id, _| game_state.island_dynamic.contains_key(id));
+        for island in game_state.island_dynamic.values() {
+            if island.owner != Some(self.player_id) {
+                self.take_progress.remove(&island.id);
+                continue;
+            }
+            let last = self.take_progress.insert(island.id, island.take_progress);
+            let is_falling = last.is_some_and(|last| island.take_progress < last);
+            if is_falling && attacked.is_none() {
+                attacked = game_state.game_map.islands.get(&island.id).cloned();
+            }
+        }
+        attacked
+    }
+
+    /// Sends the ships closest to the island, enough to outnumber the attackers
+    fn defend_island(&mut self, game_state: &ServerState, player: &Player, island: &Island) {
+        let target = island.light_house;
+        let (friendly, enemy) = self.fleet_strengths(game_state, target);
+        let needed = ((enemy - friendly) * self.params.defenders_per_enemy)
+            .ceil()
+            .max(1.0) as usize;
+        let mut ships: Vec<_> = player
+            .my_ships(game_state)
+            .into_iter()
+            .filter(|ship| ship.position.distance(target) > THREAT_RADIUS)
+            .collect();
+        ships.sort_by(|a, b| {
+            let da = a.position.distance(target);
+            let db = b.position.distance(target);
+            da.total_cmp(&db)
+        });
+        self.orders.push(BotOrder::Move {
+            ships: ships.iter().take(needed).map(|ship| ship.id).collect(),
+            target,
+        });
+    }
+
+    /// Pulls the ships fighting around the position back to the least threatened water around the safe island
+    fn retreat(&mut self, game_state: &ServerState, player: &Player, position: V2D, safe: &Island) {
+        let ships = player
+            .my_ships(game_state)
+            .iter()
+            .filter(|ship| ship.position.distance(position) < THREAT_RADIUS)
+            .map(|ship| ship.id)
+            .collect();
+        let target = self
+            .influence
+            .as_ref()
+            .and_then(|map| map.staging_point(game_state, safe.light_house, THREAT_RADIUS))
+            .unwrap_or(safe.light_house);
+        self.orders.push(BotOrder::Move { ships, target });
+    }
+
+    fn closest_friendly_island(&self, game_state: &ServerState, from: &Island) -> Option<Island> {
+        game_state
+            .island_dynamic
+            .values()
+            .filter(|island| {
+                island
+                    .owner
+                    .is_some_and(|owner| game_state.are_allies(owner, self.player_id))
+            })
+            .filter_map(|island| game_state.game_map.islands.get(&island.id))
+            .min_by(|a, b| {
+                let da = a.light_house.distance(from.light_house);
+                let db = b.light_house.distance(from.light_house);
+                da.total_cmp(&db)
+            })
+            .cloned()
     }
 
     fn my_islands(&self, game_state: &ServerState) -> usize {
         game_state
             .island_dynamic
             .values()
-            .filter(|island| return island.owner == Some(self.player.id))
+            .filter(|island| return island.owner == Some(self.player_id))
             .count()
     }
 
-    fn closes_island_not_mine(&self, game_state: &ServerState) -> Option<Island> {
+    /// Opens a route between our two closest islands if we have none
+    fn setup_trade_route(&mut self, game_state: &ServerState) {
+        let has_route = game_state
+            .trade_routes
+            .values()
+            .any(|route| route.player_id == self.player_id);
+        if has_route {
+            return;
+        }
+        let islands: Vec<_> = game_state
+            .island_dynamic
+            .values()
+            .filter(|island| island.owner == Some(self.player_id))
+            .collect();
+        let closest_pair = islands
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| islands[i + 1..].iter().map(move |b| (a, b)))
+            .min_by(|(a1, b1), (a2, b2)| {
+                let d1 = a1.lighthouse.distance(b1.lighthouse);
+                let d2 = a2.lighthouse.distance(b2.lighthouse);
+                d1.total_cmp(&d2)
+            });
+        if let Some((from, to)) = closest_pair {
+            self.orders.push(BotOrder::CreateTradeRoute {
+                from: from.id,
+                to: to.id,
+            });
+        }
+    }
+
+    /// Closest island that is not ours, weighing the distance by the enemies the
+    /// influence map sees around it and by how big the island is
+    fn closes_island_not_mine(&self, game_state: &ServerState, player: &Player) -> Option<Island> {
         let mut center_of_ships = V2D::new(0.0, 0.0);
-        let ships = self.player.my_ships(game_state);
+        let ships = player.my_ships(game_state);
         if ships.len() == 0 {
             return None;
         }
@@ -122,16 +521,201 @@ impl BotPlayer {
             .island_dynamic
             .values()
             .filter_map(|island| {
-                if island.owner == Some(self.player.id) {
+                let is_allied = island
+                    .owner
+                    .is_some_and(|owner| game_state.are_allies(owner, self.player_id));
+                if is_allied {
                     return None;
                 }
-                return game_state.game_map.islands.get(&island.id);
+                let factor = if island.owner.is_some() {
+                    self.params.enemy_island_factor
+                } else {
+                    self.params.free_island_factor
+                };
+                let island = game_state.game_map.islands.get(&island.id)?;
+                return Some((island, factor));
             })
-            .min_by_key(|&island| {
+            .min_by_key(|&(island, factor)| {
                 let pos = island.light_house;
-                let dist = center_of_ships.distance(pos);
+                let mut dist = center_of_ships.distance(pos) * factor;
+                if let Some(map) = self.influence.as_ref() {
+                    let cell = map.at(pos);
+                    dist *= (1.0 + cell.enemy) / (1.0 + cell.island_value / ISLAND_VALUE_SCALE);
+                }
                 dist as u64
             });
-        return island.cloned();
+        return island.map(|(island, _)| island.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BotConfig, BotDifficulty, BotPersonality, StandardBrain};
+    use crate::{
+        bot_brain::BotOrder,
+        island::Island,
+        player::Player,
+        server_state::ServerState,
+        ship::{ShipState, SHIP_MAX_HP},
+        storm::Storm,
+        utils::vectors::V2D,
+    };
+
+    const BOT: u64 = 1;
+    const ENEMY: u64 = 2;
+
+    /// Game with the bot owning its first island
+    fn game_with_island() -> (ServerState, Island) {
+        let mut game = ServerState::new(0);
+        let island = game.game_map.islands.values().next().unwrap().clone();
+        let island_dyn = game.island_dynamic.get_mut(&island.id).unwrap();
+        island_dyn.owner = Some(BOT);
+        island_dyn.take_progress = 1.0;
+        (game, island)
+    }
+
+    /// Adds ships at offsets from the position, returns their ids
+    fn add_ships(
+        game: &mut ServerState,
+        player_id: u64,
+        at: V2D,
+        offsets: &[(f64, f64)],
+    ) -> Vec<u64> {
+        let ids = offsets
+            .iter()
+            .map(|&offset| {
+                let ship = ShipState {
+                    id: game.next_artifact_id(),
+                    player_id,
+                    position: at + V2D::from(offset),
+                    ..Default::default()
+                };
+                game.ship_collection.insert(ship.key(), ship);
+                ship.id
+            })
+            .collect();
+        game.update_hashgrid();
+        ids
+    }
+
+    fn single_move(brain: &StandardBrain) -> (Vec<u64>, V2D) {
+        match brain.orders.as_slice() {
+            [BotOrder::Move { ships, target }] => (ships.clone(), *target),
+            orders => panic!("expected one move order, got {orders:?}"),
+        }
+    }
+
+    #[test]
+    fn test_defends_island_under_attack() {
+        let (mut game, island) = game_with_island();
+        let target = island.light_house;
+        add_ships(&mut game, ENEMY, target, &[(20.0, 0.0), (0.0, 20.0)]);
+        let defenders = add_ships(
+            &mut game,
+            BOT,
+            target,
+            &[(300.0, 0.0), (400.0, 0.0), (500.0, 0.0), (600.0, 0.0)],
+        );
+        let mut brain = StandardBrain::new(BotConfig::default(), BOT);
+        assert!(brain.island_under_attack(&game).is_none());
+
+        game.island_dynamic
+            .get_mut(&island.id)
+            .unwrap()
+            .take_progress = 0.5;
+        let attacked = brain.island_under_attack(&game).unwrap();
+        assert_eq!(attacked.id, island.id);
+        brain.defend_island(&game, &Player::new(BOT), &attacked);
+        // defenders_per_enemy is 1.5, the closest three go
+        assert_eq!(single_move(&brain), (defenders[..3].to_vec(), target));
+    }
+
+    #[test]
+    fn test_retreats_when_outgunned() {
+        let (mut game, safe) = game_with_island();
+        let position = V2D::new(0.0, 0.0);
+        let ours = add_ships(&mut game, BOT, position, &[(10.0, 0.0)]);
+        add_ships(&mut game, ENEMY, position, &[(-10.0, 0.0)]);
+        let mut brain = StandardBrain::new(BotConfig::default(), BOT);
+        assert!(!brain.is_losing_at(&game, position));
+
+        add_ships(&mut game, ENEMY, position, &[(0.0, 10.0)]);
+        assert!(brain.is_losing_at(&game, position));
+        brain.retreat(&game, &Player::new(BOT), position, &safe);
+        assert_eq!(single_move(&brain), (ours, safe.light_house));
+    }
+
+    #[test]
+    fn test_fleet_strengths_ignore_ships_hidden_by_storms() {
+        let (mut game, island) = game_with_island();
+        let target = island.light_house;
+        add_ships(&mut game, ENEMY, target, &[(20.0, 0.0), (0.0, 20.0)]);
+        let brain = StandardBrain::new(BotConfig::default(), BOT);
+        assert_eq!(brain.fleet_strengths(&game, target), (0.0, 2.0));
+
+        let storm = Storm {
+            id: 1,
+            position: target,
+            speed: V2D::new(0.0, 0.0),
+            radius: 50.0,
+            time_created: 0.0,
+            ttl: 100.0,
+        };
+        game.storms.insert(storm.id, storm);
+        assert_eq!(brain.fleet_strengths(&game, target), (0.0, 0.0));
+
+        // a ship of ours inside the storm sees through it
+        add_ships(&mut game, BOT, target, &[(-20.0, 0.0)]);
+        assert_eq!(brain.fleet_strengths(&game, target), (1.0, 2.0));
+    }
+
+    #[test]
+    fn test_can_engage_counts_the_ships_sent() {
+        let (mut game, island) = game_with_island();
+        let target = island.light_house;
+        game.island_dynamic.get_mut(&island.id).unwrap().owner = Some(ENEMY);
+        add_ships(&mut game, ENEMY, target, &[(20.0, 0.0), (0.0, 20.0)]);
+        let brain = StandardBrain::new(BotConfig::default(), BOT);
+        let player = Player::new(BOT);
+
+        // ships already at the island are not counted a second time
+        add_ships(&mut game, BOT, target, &[(-20.0, 0.0), (0.0, -20.0)]);
+        assert!(!brain.can_engage(&game, &player, &island));
+        add_ships(&mut game, BOT, target, &[(300.0, 0.0), (400.0, 0.0)]);
+        assert!(brain.can_engage(&game, &player, &island));
+
+        // damaged ships count for their hp
+        for ship in game.ship_collection.values_mut() {
+            if ship.player_id == BOT {
+                ship.hp = SHIP_MAX_HP / 2.0;
+            }
+        }
+        assert!(!brain.can_engage(&game, &player, &island));
+    }
+
+    #[test]
+    fn test_bot_params() {
+        let normal = BotConfig::default().params();
+        let hard = BotConfig {
+            difficulty: BotDifficulty::Hard,
+            ..Default::default()
+        }
+        .params();
+        assert!(hard.time_for_action < normal.time_for_action);
+        assert!(hard.shoot_radius < normal.shoot_radius);
+
+        let aggressive = BotConfig {
+            personality: BotPersonality::Aggressive,
+            ..Default::default()
+        }
+        .params();
+        let defensive = BotConfig {
+            personality: BotPersonality::Defensive,
+            ..Default::default()
+        }
+        .params();
+        assert!(aggressive.units_per_island_to_attack < defensive.units_per_island_to_attack);
+        assert!(defensive.garrison > 0.0);
+        assert!(defensive.retreat_ratio < aggressive.retreat_ratio);
     }
 }
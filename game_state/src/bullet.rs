@@ -1,11 +1,100 @@
 use cgmath::InnerSpace;
 use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use wasm_bindgen::prelude::*;
 
 use crate::utils::vectors::{V2D, V3D};
 
 const BULLET_SPEED: f64 = 100.0;
 const GRAVITY: f64 = 9.81;
 const MAX_SHOOT_ANGLE: f64 = 3.14 / 180.0 * 10.0;
+const TOTAL_HIT: f64 = 30.0;
+const BLAST_RADIUS: f64 = 20.0;
+/// Seconds a ship hit by chain shot stays slowed
+pub const CHAIN_SHOT_SLOW_TIME: f64 = 10.0;
+const INTERCEPT_ITERATIONS: usize = 8;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize_repr, Deserialize_repr, PartialEq, Default)]
+#[repr(u8)]
+pub enum AmmoKind {
+    #[default]
+    RoundShot,
+    /// Weak, but slows down the ships it hits
+    ChainShot,
+    /// Short range and wide spread, hits hard around the impact
+    Grapeshot,
+    /// Long range and strong, but the cannon takes longer to reload
+    HeavyShot,
+}
+
+impl AmmoKind {
+    pub const ALL: [AmmoKind; 4] = [
+        AmmoKind::RoundShot,
+        AmmoKind::ChainShot,
+        AmmoKind::Grapeshot,
+        AmmoKind::HeavyShot,
+    ];
+
+    fn speed(&self) -> f64 {
+        match self {
+            AmmoKind::RoundShot => BULLET_SPEED,
+            AmmoKind::ChainShot => BULLET_SPEED * 0.9,
+            AmmoKind::Grapeshot => BULLET_SPEED * 0.7,
+            AmmoKind::HeavyShot => BULLET_SPEED * 1.2,
+        }
+    }
+
+    fn total_hit(&self) -> f64 {
+        match self {
+            AmmoKind::RoundShot => TOTAL_HIT,
+            AmmoKind::ChainShot => TOTAL_HIT / 3.0,
+            AmmoKind::Grapeshot => TOTAL_HIT * 1.2,
+            AmmoKind::HeavyShot => TOTAL_HIT * 1.5,
+        }
+    }
+
+    pub fn blast_radius(&self) -> f64 {
+        match self {
+            AmmoKind::Grapeshot => BLAST_RADIUS * 1.5,
+            AmmoKind::ChainShot => BLAST_RADIUS * 0.75,
+            _ => BLAST_RADIUS,
+        }
+    }
+
+    /// Multiplies the reload time of the cannon that fired it
+    pub fn reload_factor(&self) -> f64 {
+        match self {
+            AmmoKind::Grapeshot => 0.8,
+            AmmoKind::HeavyShot => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Multiplies the shooting error
+    pub fn spread(&self) -> f64 {
+        match self {
+            AmmoKind::Grapeshot => 3.0,
+            _ => 1.0,
+        }
+    }
+
+    pub fn damage(&self, distance: f64) -> f64 {
+        let blast_radius = self.blast_radius();
+        if distance < blast_radius {
+            let hit_factor = 1.0 - distance / blast_radius;
+            return self.total_hit() * hit_factor * hit_factor;
+        }
+        0.0
+    }
+
+    pub fn max_distance(&self) -> f64 {
+        let v_horizontal = self.speed() * f64::cos(MAX_SHOOT_ANGLE);
+        let v_vertical = self.speed() * f64::sin(MAX_SHOOT_ANGLE);
+        let time = v_vertical * 2.0 / GRAVITY;
+        v_horizontal * time
+    }
+}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct Bullet {
@@ -13,6 +102,9 @@ pub struct Bullet {
     pub speed: V3D,
     pub player_id: u64,
     pub bullet_id: u64,
+    /// Ship that fired the bullet, None for forts
+    pub ship_id: Option<u64>,
+    pub ammo: AmmoKind,
     pub target: V3D,
     pub time: f64,
 }
@@ -22,19 +114,24 @@ pub struct BulletSnapShot {
     pub position: V3D,
     pub player_id: u64,
     pub bullet_id: u64,
+    pub ammo: AmmoKind,
 }
 
 impl Bullet {
+    /// Range of the ammo that goes further
     pub fn max_distance() -> f64 {
-        let v_horizontal = BULLET_SPEED * f64::cos(MAX_SHOOT_ANGLE);
-        let v_vertical = BULLET_SPEED * f64::sin(MAX_SHOOT_ANGLE);
-        let time = v_vertical * 2.0 / GRAVITY;
-        let distance = v_horizontal * time;
-        return distance;
+        AmmoKind::ALL
+            .iter()
+            .map(|ammo| ammo.max_distance())
+            .fold(0.0, f64::max)
     }
 
     pub fn maybe_from_target(initial: V2D, target: V2D) -> Option<Bullet> {
-        let v0 = BULLET_SPEED;
+        Self::maybe_from_target_with(initial, target, AmmoKind::RoundShot)
+    }
+
+    pub fn maybe_from_target_with(initial: V2D, target: V2D, ammo: AmmoKind) -> Option<Bullet> {
+        let v0 = ammo.speed();
         let g = GRAVITY;
         let initial: V3D = (initial.x, initial.y, 0.0).into();
         let target: V3D = (target.x, target.y, 0.0).into();
@@ -62,16 +159,32 @@ impl Bullet {
             speed,
             player_id: 0,
             bullet_id: 0,
+            ship_id: None,
+            ammo,
             target: target.into(),
             time: 0.0,
         });
     }
 
+    /// Where to aim so the bullet meets a target moving with constant speed.
+    /// The flight time to the aim point moves the aim point, this repeats until it settles.
+    /// None when the aim point is out of range.
+    pub fn intercept(initial: V2D, target: V2D, target_speed: V2D, ammo: AmmoKind) -> Option<V2D> {
+        let mut aim = target;
+        for _ in 0..INTERCEPT_ITERATIONS {
+            let bullet = Self::maybe_from_target_with(initial, aim, ammo)?;
+            aim = target + target_speed * bullet.end_time();
+        }
+        Self::maybe_from_target_with(initial, aim, ammo)?;
+        Some(aim)
+    }
+
     pub fn snapshot(&self) -> BulletSnapShot {
         BulletSnapShot {
             position: self.current_pos().into(),
             player_id: self.player_id,
             bullet_id: self.bullet_id,
+            ammo: self.ammo,
         }
     }
 
@@ -108,7 +221,8 @@ impl Bullet {
 mod test {
     use cgmath::MetricSpace;
 
-    use super::Bullet;
+    use super::{AmmoKind, Bullet};
+    use crate::utils::vectors::V2D;
     const BLAST_RADIUS: f64 = 1.0;
 
     fn verify_hits_target(initial: (f64, f64), target: (f64, f64)) -> bool {
@@ -124,4 +238,39 @@ mod test {
         assert!(verify_hits_target((0.0, 0.0), (0.0, 3.0)));
         assert!(verify_hits_target((0.0, 0.0), (1000.0, 1000.0)));
     }
+
+    #[test]
+    fn test_intercept() {
+        let initial = V2D::new(0.0, 0.0);
+        let target = V2D::new(200.0, 0.0);
+        let target_speed = V2D::new(0.0, 16.0);
+        let aim = Bullet::intercept(initial, target, target_speed, AmmoKind::RoundShot).unwrap();
+        let bullet = Bullet::maybe_from_target(initial, aim).unwrap();
+        let hit = bullet.final_pos();
+        let target_then = target + target_speed * bullet.end_time();
+        assert!(hit.distance((target_then.x, target_then.y, 0.0).into()) < BLAST_RADIUS);
+
+        let running_away = V2D::new(16.0, 0.0);
+        let edge = V2D::new(AmmoKind::RoundShot.max_distance() - 1.0, 0.0);
+        assert!(Bullet::intercept(initial, edge, running_away, AmmoKind::RoundShot).is_none());
+    }
+
+    #[test]
+    fn test_ammo_kinds() {
+        let round = AmmoKind::RoundShot;
+        let heavy = AmmoKind::HeavyShot;
+        let grape = AmmoKind::Grapeshot;
+        assert!(heavy.max_distance() > round.max_distance());
+        assert!(grape.max_distance() < round.max_distance());
+        assert_eq!(Bullet::max_distance(), heavy.max_distance());
+        assert!(heavy.damage(0.0) > round.damage(0.0));
+        assert!(AmmoKind::ChainShot.damage(0.0) < round.damage(0.0));
+        assert!(grape.damage(round.blast_radius()) > 0.0);
+        assert_eq!(round.damage(round.blast_radius()), 0.0);
+
+        let target = (0.0, round.max_distance() * 1.1).into();
+        assert!(Bullet::maybe_from_target((0.0, 0.0).into(), target).is_none());
+        let bullet = Bullet::maybe_from_target_with((0.0, 0.0).into(), target, heavy);
+        assert_eq!(bullet.map(|bullet| bullet.ammo), Some(heavy));
+    }
 }
@@ -0,0 +1,138 @@
+use crate::{game_map::WorldGrid, game_noise::GameNoise, utils::vectors::V2D};
+use cgmath::InnerSpace;
+
+/// m/s of the current far from any island
+const OPEN_WATER_CURRENT: f64 = 1.0;
+/// m/s of the current in the narrowest straits
+const STRAIT_CURRENT: f64 = 4.0;
+/// How many tiles we walk in each direction looking for land
+const STRAIT_SEARCH_TILES: i32 = 15;
+const TILES_PER_CELL: f64 = 2.0;
+const NOISE_SCALE: f64 = 0.0005;
+
+/// Deterministic ocean current field generated from the world seed.
+/// The direction comes from the curl of a noise field, so the flow has no sources or sinks,
+/// and the strength grows where the water is squeezed between islands.
+#[derive(Clone, Default)]
+pub struct CurrentField {
+    dim: f64,
+    cell_size: f64,
+    cells_dim: usize,
+    cells: Vec<V2D>,
+}
+
+impl CurrentField {
+    pub fn new(grid: &WorldGrid, noise: &GameNoise) -> Self {
+        let cell_size = grid.tile_size * TILES_PER_CELL;
+        let cells_dim = (grid.dim / cell_size).ceil() as usize;
+        let mut field = Self {
+            dim: grid.dim,
+            cell_size,
+            cells_dim,
+            cells: vec![V2D::new(0.0, 0.0); cells_dim * cells_dim],
+        };
+        for y in 0..cells_dim {
+            for x in 0..cells_dim {
+                let pos_x = field.cell_center(x);
+                let pos_y = field.cell_center(y);
+                let strait = strait_factor(grid, pos_x, pos_y);
+                let strait = if let Some(strait) = strait {
+                    strait
+                } else {
+                    continue;
+                };
+                let strength = OPEN_WATER_CURRENT + (STRAIT_CURRENT - OPEN_WATER_CURRENT) * strait;
+                let direction = curl_direction(noise, pos_x, pos_y, cell_size);
+                field.cells[y * cells_dim + x] = direction * strength;
+            }
+        }
+        field
+    }
+
+    pub fn max_strength() -> f64 {
+        STRAIT_CURRENT
+    }
+
+    fn cell_unit(&self, val: f64) -> Option<usize> {
+        let unit = ((val + self.dim / 2.0) / self.cell_size).floor();
+        if unit < 0.0 || unit >= self.cells_dim as f64 {
+            return None;
+        }
+        Some(unit as usize)
+    }
+
+    fn cell_center(&self, unit: usize) -> f64 {
+        (unit as f64 + 0.5) * self.cell_size - self.dim / 2.0
+    }
+
+    /// Current velocity at a point, zero on land or outside the map
+    pub fn at(&self, x: f64, y: f64) -> V2D {
+        let cell = self
+            .cell_unit(x)
+            .zip(self.cell_unit(y))
+            .and_then(|(x, y)| self.cells.get(y * self.cells_dim + x));
+        cell.copied().unwrap_or(V2D::new(0.0, 0.0))
+    }
+}
+
+fn curl_direction(noise: &GameNoise, x: f64, y: f64, h: f64) -> V2D {
+    let n = noise.get(x * NOISE_SCALE, y * NOISE_SCALE);
+    let n_x = noise.get((x + h) * NOISE_SCALE, y * NOISE_SCALE);
+    let n_y = noise.get(x * NOISE_SCALE, (y + h) * NOISE_SCALE);
+    let curl = V2D::new(n_y - n, n - n_x);
+    if curl.magnitude2() == 0.0 {
+        return curl;
+    }
+    curl.normalize()
+}
+
+/// 1.0 for the narrowest straits and 0.0 for open water.
+/// None if the point is not water.
+fn strait_factor(grid: &WorldGrid, x: f64, y: f64) -> Option<f64> {
+    if !grid.get(x, y)?.is_water() {
+        return None;
+    }
+    let land_distance = |dx: i32, dy: i32| {
+        (1..=STRAIT_SEARCH_TILES).find(|&i| {
+            let x = x + (dx * i) as f64 * grid.tile_size;
+            let y = y + (dy * i) as f64 * grid.tile_size;
+            if x.abs() >= grid.dim / 2.0 || y.abs() >= grid.dim / 2.0 {
+                return false;
+            }
+            grid.get(x, y).map(|tile| tile.is_land()).unwrap_or(false)
+        })
+    };
+    let width_between = |a: Option<i32>, b: Option<i32>| {
+        let width = (a? + b?) as f64;
+        Some(1.0 - width / (2.0 * STRAIT_SEARCH_TILES as f64))
+    };
+    let horizontal = width_between(land_distance(1, 0), land_distance(-1, 0));
+    let vertical = width_between(land_distance(0, 1), land_distance(0, -1));
+    let factor = horizontal.unwrap_or(0.0).max(vertical.unwrap_or(0.0));
+    Some(factor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::CurrentField;
+    use crate::{
+        game_map::{Tile, WorldGrid},
+        game_noise::GameNoise,
+    };
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn test_strait_is_stronger() {
+        let mut grid = WorldGrid::new(400.0, Tile::default(), 10.0);
+        for y in -20..20 {
+            let y = y as f64 * 10.0;
+            grid.set(-30.0, y, Tile::grass(10.0));
+            grid.set(30.0, y, Tile::grass(10.0));
+        }
+        let field = CurrentField::new(&grid, &GameNoise::new(Some(1)));
+        let strait = field.at(0.0, 0.0).magnitude();
+        let open = field.at(150.0, 150.0).magnitude();
+        assert!(strait > open);
+        assert_eq!(field.at(30.0, 0.0).magnitude(), 0.0);
+    }
+}
@@ -0,0 +1,192 @@
+use cgmath::{InnerSpace, MetricSpace};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    server_state::ServerState,
+    utils::{spiral_search::SpiralSearch, vectors::V2D},
+};
+
+/// Distance between neighbour ships in a formation, a bit more than flocking keeps them apart
+const FORMATION_SPACING: f64 = 25.0;
+const SPIRAL_CELL_SIZE: f64 = 20.0;
+/// Cells searched around a slot on land for a place in the water
+const MAX_SLOT_SEARCH: usize = 49;
+/// Passes of pairwise swaps when assigning ships to slots
+const ASSIGNMENT_PASSES: usize = 4;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum Formation {
+    /// Ships packed around the target
+    #[default]
+    Spiral,
+    /// Side by side across the direction of travel
+    LineAbreast,
+    /// One behind the other, the first one at the target
+    Column,
+    /// A V with the tip at the target
+    Wedge,
+    Circle,
+}
+
+impl Formation {
+    /// Slot offsets, x along the direction of travel and y to its left
+    fn offsets(&self, n: usize) -> Vec<V2D> {
+        let spacing = FORMATION_SPACING;
+        (0..n)
+            .map(|i| match self {
+                Formation::Spiral => V2D::new(0.0, 0.0),
+                Formation::LineAbreast => {
+                    V2D::new(0.0, (i as f64 - (n as f64 - 1.0) / 2.0) * spacing)
+                }
+                Formation::Column => V2D::new(-(i as f64) * spacing, 0.0),
+                Formation::Wedge => {
+                    let row = i.div_ceil(2) as f64;
+                    let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                    V2D::new(-row * spacing, side * row * spacing)
+                }
+                Formation::Circle => {
+                    if n == 1 {
+                        return V2D::new(0.0, 0.0);
+                    }
+                    let radius = (n as f64 * spacing / std::f64::consts::TAU).max(spacing);
+                    let angle = i as f64 / n as f64 * std::f64::consts::TAU;
+                    V2D::new(angle.cos(), angle.sin()) * radius
+                }
+            })
+            .collect()
+    }
+
+    /// Where each of the n ships should end up, facing the direction of travel.
+    /// Slots that fall on land move to the closest water around them.
+    pub fn slots(&self, n: usize, target: V2D, direction: V2D, game: &ServerState) -> Vec<V2D> {
+        if *self == Formation::Spiral {
+            return spiral_slots(n, target, game);
+        }
+        place(&self.offsets(n), target, direction)
+            .into_iter()
+            .map(|slot| closest_allowed(slot, game))
+            .collect()
+    }
+}
+
+/// Turns offsets into world positions around the target
+fn place(offsets: &[V2D], target: V2D, direction: V2D) -> Vec<V2D> {
+    let forward = if direction.magnitude2() > 0.0 {
+        direction.normalize()
+    } else {
+        V2D::new(1.0, 0.0)
+    };
+    let left = V2D::new(-forward.y, forward.x);
+    offsets
+        .iter()
+        .map(|offset| target + forward * offset.x + left * offset.y)
+        .collect()
+}
+
+fn spiral_slots(n: usize, target: V2D, game: &ServerState) -> Vec<V2D> {
+    SpiralSearch::new((0, 0))
+        .map(|(x, y)| target + V2D::new(x as f64, y as f64) * SPIRAL_CELL_SIZE)
+        .filter(|slot| game.game_map.is_allowed_place(slot.x, slot.y))
+        .take(n)
+        .collect()
+}
+
+fn closest_allowed(slot: V2D, game: &ServerState) -> V2D {
+    SpiralSearch::new((0, 0))
+        .take(MAX_SLOT_SEARCH)
+        .map(|(x, y)| slot + V2D::new(x as f64, y as f64) * SPIRAL_CELL_SIZE / 2.0)
+        .find(|place| game.game_map.is_allowed_place(place.x, place.y))
+        .unwrap_or(slot)
+}
+
+/// Index of the slot of each ship. Ships and slots are first matched in the same order
+/// across the direction of travel, then pairs swap slots while that shortens their trips,
+/// which also undoes paths that cross.
+pub fn assign_slots(ships: &[V2D], slots: &[V2D], direction: V2D) -> Vec<usize> {
+    let left = V2D::new(-direction.y, direction.x);
+    let order = |points: &[V2D]| {
+        let mut indexes: Vec<usize> = (0..points.len()).collect();
+        indexes.sort_by(|&a, &b| {
+            let (a, b) = (points[a], points[b]);
+            a.dot(left)
+                .total_cmp(&b.dot(left))
+                .then(a.dot(direction).total_cmp(&b.dot(direction)))
+        });
+        indexes
+    };
+    let mut assignment = vec![0; ships.len()];
+    for (ship, slot) in order(ships).into_iter().zip(order(slots)) {
+        assignment[ship] = slot;
+    }
+    for _ in 0..ASSIGNMENT_PASSES {
+        let mut swapped = false;
+        for a in 0..ships.len() {
+            for b in a + 1..ships.len() {
+                let (slot_a, slot_b) = (slots[assignment[a]], slots[assignment[b]]);
+                let current = ships[a].distance(slot_a) + ships[b].distance(slot_b);
+                let exchanged = ships[a].distance(slot_b) + ships[b].distance(slot_a);
+                if exchanged < current - f64::EPSILON {
+                    assignment.swap(a, b);
+                    swapped = true;
+                }
+            }
+        }
+        if !swapped {
+            break;
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod test {
+    use super::{assign_slots, place, Formation};
+    use crate::utils::vectors::V2D;
+    use cgmath::MetricSpace;
+
+    #[test]
+    fn test_formation_faces_travel() {
+        let target = V2D::new(100.0, 0.0);
+        let north = V2D::new(0.0, 1.0);
+        let line = place(&Formation::LineAbreast.offsets(3), target, north);
+        assert!(line.iter().all(|slot| (slot.y - target.y).abs() < 1e-9));
+        let column = place(&Formation::Column.offsets(3), target, north);
+        assert!(column.iter().all(|slot| (slot.x - target.x).abs() < 1e-9));
+        assert!(column[1].y < target.y);
+
+        let wedge = place(&Formation::Wedge.offsets(5), target, north);
+        assert_eq!(wedge[0], target);
+        assert!(wedge[1..].iter().all(|slot| slot.y < target.y));
+        assert_eq!(wedge[1].y, wedge[2].y);
+
+        let circle = place(&Formation::Circle.offsets(8), target, north);
+        let radius = circle[0].distance(target);
+        assert!(radius > 0.0);
+        assert!(circle[0].y > target.y);
+        assert!(circle
+            .iter()
+            .all(|slot| (slot.distance(target) - radius).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_assignment_does_not_cross() {
+        let ships = [
+            V2D::new(0.0, 0.0),
+            V2D::new(0.0, 50.0),
+            V2D::new(0.0, 100.0),
+        ];
+        let slots = [
+            V2D::new(200.0, 100.0),
+            V2D::new(200.0, 0.0),
+            V2D::new(200.0, 50.0),
+        ];
+        let assignment = assign_slots(&ships, &slots, V2D::new(1.0, 0.0));
+        assert_eq!(assignment, vec![1, 2, 0]);
+
+        // a bad start order still ends without crossing paths
+        let assignment = assign_slots(&ships, &slots, V2D::new(0.0, 0.0));
+        assert_eq!(assignment, vec![1, 2, 0]);
+    }
+}
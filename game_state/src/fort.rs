@@ -0,0 +1,116 @@
+use crate::{
+    bullet::{AmmoKind, Bullet},
+    utils::vectors::V2D,
+};
+use serde::{Deserialize, Serialize};
+
+pub const FORT_MAX_HP: f64 = 300.0;
+/// hp gained per second while the fort is being built
+const FORT_BUILD_PER_SECOND: f64 = 10.0;
+const FORT_RELOAD_TIME: f64 = 3.0;
+/// Bullets landing this close to the lighthouse damage the fort
+pub const FORT_SIZE: f64 = 15.0;
+/// Resources spent to start building a fort
+pub const FORT_COST: usize = 50;
+
+/// Fortified lighthouse, shoots enemy ships close to its island.
+/// The island stops producing ships while the fort is being built.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Fort {
+    pub hp: f64,
+    /// Becomes true once the fort reaches full hp for the first time
+    pub built: bool,
+    pub last_shoot_time: f64,
+}
+
+impl Default for Fort {
+    fn default() -> Self {
+        Self {
+            hp: 1.0,
+            built: false,
+            last_shoot_time: 0.0,
+        }
+    }
+}
+
+impl Fort {
+    /// Built forts do not repair, or shooting them would never get through
+    pub fn evolve(&mut self, dt: f64) {
+        if self.built {
+            return;
+        }
+        self.hp = (self.hp + FORT_BUILD_PER_SECOND * dt).min(FORT_MAX_HP);
+        if self.hp >= FORT_MAX_HP {
+            self.built = true;
+        }
+    }
+
+    pub fn is_destroyed(&self) -> bool {
+        self.hp <= 0.0
+    }
+
+    pub fn can_shoot(&self, current_time: f64) -> bool {
+        self.built && current_time - self.last_shoot_time > FORT_RELOAD_TIME
+    }
+
+    pub fn range() -> f64 {
+        AmmoKind::RoundShot.max_distance()
+    }
+
+    pub fn shoot_at(
+        &mut self,
+        current_time: f64,
+        position: V2D,
+        target: V2D,
+        player_id: u64,
+    ) -> Option<Bullet> {
+        if !self.can_shoot(current_time) {
+            return None;
+        }
+        let bullet = Bullet {
+            player_id,
+            ..Bullet::maybe_from_target(position, target)?
+        };
+        self.last_shoot_time = current_time;
+        Some(bullet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Fort, FORT_MAX_HP};
+    use crate::utils::vectors::V2D;
+
+    #[test]
+    fn test_fort_builds_before_shooting() {
+        let mut fort = Fort::default();
+        let target = V2D::new(50.0, 0.0);
+        assert!(fort
+            .shoot_at(100.0, V2D::new(0.0, 0.0), target, 1)
+            .is_none());
+        for _ in 0..60 {
+            fort.evolve(1.0);
+        }
+        assert_eq!(fort.hp, FORT_MAX_HP);
+        let bullet = fort.shoot_at(100.0, V2D::new(0.0, 0.0), target, 1);
+        assert_eq!(bullet.map(|bullet| bullet.player_id), Some(1));
+        assert!(fort
+            .shoot_at(101.0, V2D::new(0.0, 0.0), target, 1)
+            .is_none());
+    }
+
+    #[test]
+    fn test_built_fort_does_not_heal() {
+        let mut fort = Fort {
+            hp: FORT_MAX_HP,
+            built: true,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            fort.hp -= 20.0;
+            fort.evolve(1.0);
+        }
+        assert_eq!(fort.hp, FORT_MAX_HP - 200.0);
+        assert!(fort.built);
+    }
+}
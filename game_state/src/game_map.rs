@@ -1,4 +1,4 @@
-use cgmath::Vector2;
+use cgmath::{InnerSpace, Vector2};
 use hierarchical_pathfinding::{prelude::MooreNeighborhood, PathCache, PathCacheConfig};
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -8,12 +8,21 @@ use std::{
 use wasm_bindgen::prelude::*;
 
 use crate::{
+    currents::CurrentField,
     island::{Island, IslandData, IslandTile},
     utils::{spiral_search::SpiralSearch, vectors::V2D},
 };
 
 const MIN_ISLAND_SIZE: usize = 150;
 const LAND_WALK_COST: isize = -1;
+const WATER_WALK_COST: isize = 4;
+/// How much a current at full strength changes the walk cost of a tile inside a chunk
+const CURRENT_WALK_COST: f64 = 2.0;
+/// Distance from the coast a pulled path keeps, in tiles
+const PATH_CLEARANCE: f64 = 0.5;
+/// Part of each side of a corner that smoothing cuts away
+const CORNER_CUT: f64 = 0.25;
+const SMOOTHING_ITERATIONS: usize = 2;
 
 impl Default for WorldGrid {
     fn default() -> Self {
@@ -26,6 +35,7 @@ impl Default for WorldGrid {
             path_cache: None,
             total_island_tiles: 0,
             small_islands: BTreeMap::new(),
+            currents: CurrentField::default(),
         }
     }
 }
@@ -129,6 +139,7 @@ pub struct WorldGrid {
     pub small_islands: BTreeMap<u64, Island>,
     pub path_cache: Option<PathCache<MooreNeighborhood>>,
     pub total_island_tiles: usize,
+    pub currents: CurrentField,
 }
 
 impl WorldGrid {
@@ -183,6 +194,7 @@ impl WorldGrid {
             path_cache: None,
             total_island_tiles: 0,
             small_islands: BTreeMap::new(),
+            currents: CurrentField::default(),
         }
     }
 
@@ -477,7 +489,7 @@ impl WorldGrid {
         self.island_data(island.id)
     }
 
-    fn can_go_straight(&self, initial: &V2D, fin: &V2D) -> bool {
+    pub fn can_go_straight(&self, initial: &V2D, fin: &V2D) -> bool {
         let mut line =
             grid_line::GridLinePath::new(initial.clone(), fin.clone(), self.tile_size / 2.0);
         while let Some(point) = line.next() {
@@ -488,12 +500,103 @@ impl WorldGrid {
         return true;
     }
 
+    /// Like can_go_straight, but a band of the given width on each side of the line
+    /// must be navigable too.
+    pub fn has_clearance(&self, initial: &V2D, fin: &V2D, clearance: f64) -> bool {
+        let direction = fin - initial;
+        if direction.magnitude2() == 0.0 {
+            return self.is_allowed_place(initial.x, initial.y);
+        }
+        let direction = direction.normalize();
+        let side = V2D::new(-direction.y, direction.x) * clearance;
+        self.can_go_straight(initial, fin)
+            && self.can_go_straight(&(initial + side), &(fin + side))
+            && self.can_go_straight(&(initial - side), &(fin - side))
+    }
+
+    /// String pulling: keeps only the waypoints where the line of sight with clearance
+    /// breaks. Steps that are already blocked are kept as they are.
+    /// Straight stretches drop whatever detour the currents asked for inside them.
+    fn pull_string(&self, path: &[V2D]) -> Vec<V2D> {
+        let clearance = self.tile_size * PATH_CLEARANCE;
+        let mut result = Vec::new();
+        let Some(&first) = path.first() else {
+            return result;
+        };
+        result.push(first);
+        let mut anchor = first;
+        let mut i = 1;
+        while i < path.len() {
+            let can_skip =
+                i + 1 < path.len() && self.has_clearance(&anchor, &path[i + 1], clearance);
+            if !can_skip {
+                anchor = path[i];
+                result.push(anchor);
+            }
+            i += 1;
+        }
+        result
+    }
+
+    /// Rounds the corners of a path by cutting them, only where the cut keeps clearance
+    /// from the coast. Adds waypoints, so it is meant for routes that are drawn or
+    /// followed without turn anticipation.
+    pub fn smooth_path(&self, path: &[V2D]) -> Vec<V2D> {
+        let clearance = self.tile_size * PATH_CLEARANCE;
+        let mut path = path.to_vec();
+        for _ in 0..SMOOTHING_ITERATIONS {
+            if path.len() < 3 {
+                break;
+            }
+            let mut smooth = vec![path[0]];
+            for window in path.windows(3) {
+                let (previous, corner, next) = (window[0], window[1], window[2]);
+                let before = corner + (previous - corner) * CORNER_CUT;
+                let after = corner + (next - corner) * CORNER_CUT;
+                if self.has_clearance(&before, &after, clearance) {
+                    smooth.push(before);
+                    smooth.push(after);
+                } else {
+                    smooth.push(corner);
+                }
+            }
+            smooth.push(path[path.len() - 1]);
+            path = smooth;
+        }
+        path
+    }
+
     fn walk_cost(&self, x: usize, y: usize) -> isize {
         self.get_usize(x, y)
-            .map(|tile| if tile.is_water() { 1 } else { LAND_WALK_COST })
+            .map(|tile| {
+                if tile.is_water() {
+                    WATER_WALK_COST
+                } else {
+                    LAND_WALK_COST
+                }
+            })
             .unwrap_or(LAND_WALK_COST)
     }
 
+    /// Walk cost that gets cheaper when the current pushes us in the travel direction.
+    /// This is only a local bias: the path cache is built with the neutral cost, so the
+    /// route between chunks ignores currents, and only the segments resolved inside the
+    /// chunks at query time see them. The travel direction is the straight line from
+    /// start to end, the same for the whole path.
+    fn current_walk_cost(&self, x: usize, y: usize, travel: V2D) -> isize {
+        let cost = self.walk_cost(x, y);
+        if cost < 0 {
+            return cost;
+        }
+        let half_tile = self.tile_size / 2.0;
+        let current = self.currents.at(
+            self.from_tile_unit(x) + half_tile,
+            self.from_tile_unit(y) + half_tile,
+        );
+        let push = current.dot(travel) / CurrentField::max_strength();
+        cost - (push * CURRENT_WALK_COST).round() as isize
+    }
+
     pub fn find_path(&self, initial: impl Into<V2D>, fin: impl Into<V2D>) -> Option<Vec<V2D>> {
         let initial = initial.into();
         let fin = fin.into();
@@ -504,6 +607,8 @@ impl WorldGrid {
             return Some(vec![initial.into(), fin.into()]);
         }
 
+        let travel = (fin - initial).normalize();
+        let (start, end) = (initial, fin);
         let initial = Vector2::new(self.tile_unit(initial.x), self.tile_unit(initial.y));
         let fin = Vector2::new(self.tile_unit(fin.x), self.tile_unit(fin.y));
         let is_target_water = self.get_usize(fin.x as usize, fin.y as usize)?.is_water();
@@ -513,10 +618,10 @@ impl WorldGrid {
 
         let path_cache = self.path_cache.as_ref()?;
         let path = path_cache.find_path(initial.into(), fin.into(), |(x, y)| {
-            return self.walk_cost(x, y);
+            return self.current_walk_cost(x, y, travel);
         })?;
 
-        let v: Vec<Vector2<f64>> = path
+        let mut v: Vec<Vector2<f64>> = path
             .take(MAX_SEARCH)
             .map(|(x, y)| {
                 let half_tile = self.tile_size / 2.0;
@@ -526,7 +631,16 @@ impl WorldGrid {
                 )
             })
             .collect();
-        return Some(v);
+        // the search works on tile centres, the ends go back to the exact positions
+        if v.len() < 2 {
+            return Some(vec![start, end]);
+        }
+        v[0] = start;
+        if v.len() < MAX_SEARCH {
+            let last = v.len() - 1;
+            v[last] = end;
+        }
+        return Some(self.pull_string(&v));
     }
 }
 
@@ -645,6 +759,37 @@ mod test {
         println!("{:?}", path);
     }
 
+    #[test]
+    fn test_pathfinding_pulls_string() {
+        let mut grid = WorldGrid::new(200.0, Tile::new(TileKind::NavWater, 0.0), 10.0);
+        for y in 0..16 {
+            grid.set(5.0, -100.0 + y as f64 * 10.0, Tile::grass(10.0));
+        }
+        grid.calc_path_cache();
+        let start = V2D::new(-50.0, -80.0);
+        let end = V2D::new(60.0, -80.0);
+        let path = grid.find_path(start, end).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&end));
+        // around the corner of the wall, not tile by tile
+        assert!(path.len() <= 5, "{:?}", path);
+        for segment in path.windows(2) {
+            assert!(grid.can_go_straight(&segment[0], &segment[1]));
+        }
+
+        // corners next to the coast are left alone
+        assert_eq!(grid.smooth_path(&path), path);
+        let open_water = [
+            V2D::new(-80.0, 80.0),
+            V2D::new(-60.0, 80.0),
+            V2D::new(-60.0, -80.0),
+        ];
+        let smooth = grid.smooth_path(&open_water);
+        assert!(smooth.len() > open_water.len());
+        assert_eq!(smooth.first(), open_water.first());
+        assert_eq!(smooth.last(), open_water.last());
+    }
+
     #[test]
     fn test_pathfinding_impossible() {
         let grid = WorldGrid::new(80.0, Tile::default(), 10.0);
@@ -20,6 +20,13 @@ impl HashEntity {
         }
         return None;
     }
+
+    pub fn as_lighthouse(&self) -> Option<(u64, V2D)> {
+        match self.entity {
+            HashEntityKind::Lighthouse(island_id) => Some((island_id, self.position)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -0,0 +1,200 @@
+use crate::{
+    fort::{Fort, FORT_MAX_HP},
+    server_state::ServerState,
+    ship::SHIP_MAX_HP,
+    utils::vectors::V2D,
+};
+use cgmath::MetricSpace;
+use serde::Serialize;
+
+/// World tiles on each side of an influence cell
+const TILES_PER_CELL: f64 = 8.0;
+/// Ships spread their strength over this distance, fading linearly
+const INFLUENCE_RADIUS: f64 = 150.0;
+/// Islands spread their value over this distance
+const ISLAND_VALUE_RADIUS: f64 = 100.0;
+/// A finished fort at full hp counts as this many ships
+const FORT_STRENGTH: f64 = 5.0;
+/// Seconds between two computations of the map
+pub const INFLUENCE_UPDATE_TIME: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct InfluenceCell {
+    /// Hp of our and allied ships around, a ship at full hp counts as 1
+    pub friendly: f64,
+    pub enemy: f64,
+    /// Island tiles around the cell that are not ours or our allies'
+    pub island_value: f64,
+    /// Enemy strength not matched by friendly strength
+    pub threat: f64,
+}
+
+/// What one player knows about the strengths around the map, over a coarse grid covering the WorldGrid.
+/// Ships hidden by storms are left out, so it only uses what the player can see.
+#[derive(Debug, Clone, Serialize)]
+pub struct InfluenceMap {
+    pub player_id: u64,
+    pub dim: f64,
+    pub cell_size: f64,
+    pub cells_dim: usize,
+    pub cells: Vec<InfluenceCell>,
+    pub computed_at: f64,
+}
+
+impl InfluenceMap {
+    pub fn new(player_id: u64, dim: f64, cell_size: f64) -> Self {
+        let cells_dim = (dim / cell_size).ceil() as usize;
+        Self {
+            player_id,
+            dim,
+            cell_size,
+            cells_dim,
+            cells: vec![InfluenceCell::default(); cells_dim * cells_dim],
+            computed_at: 0.0,
+        }
+    }
+
+    pub fn compute(game_state: &ServerState, player_id: u64) -> Self {
+        let game_map = &game_state.game_map;
+        let mut map = Self::new(player_id, game_map.dim, game_map.tile_size * TILES_PER_CELL);
+        map.computed_at = game_state.current_time;
+
+        for ship in game_state.visible_ships(player_id) {
+            if ship.is_merchant() || ship.is_sinking() {
+                continue;
+            }
+            let strength = ship.hp / SHIP_MAX_HP;
+            let is_friendly = game_state.are_allies(player_id, ship.player_id);
+            map.spread(ship.position, INFLUENCE_RADIUS, |cell, weight| {
+                if is_friendly {
+                    cell.friendly += strength * weight;
+                } else {
+                    cell.enemy += strength * weight;
+                }
+            });
+        }
+
+        for island in game_state.island_dynamic.values() {
+            let is_friendly = island
+                .owner
+                .is_some_and(|owner| game_state.are_allies(player_id, owner));
+            if !is_friendly {
+                let value = island.tiles as f64;
+                map.spread(island.lighthouse, ISLAND_VALUE_RADIUS, |cell, weight| {
+                    cell.island_value += value * weight;
+                });
+            }
+            let fort = match island.fort.as_ref().filter(|fort| fort.built) {
+                Some(fort) => fort,
+                None => continue,
+            };
+            let strength = FORT_STRENGTH * fort.hp / FORT_MAX_HP;
+            map.spread(island.lighthouse, Fort::range(), |cell, weight| {
+                if is_friendly {
+                    cell.friendly += strength * weight;
+                } else {
+                    cell.enemy += strength * weight;
+                }
+            });
+        }
+
+        for cell in map.cells.iter_mut() {
+            cell.threat = (cell.enemy - cell.friendly).max(0.0);
+        }
+        map
+    }
+
+    pub fn needs_update(&self, current_time: f64) -> bool {
+        current_time - self.computed_at > INFLUENCE_UPDATE_TIME
+    }
+
+    fn cell_unit(&self, val: f64) -> Option<usize> {
+        let unit = ((val + self.dim / 2.0) / self.cell_size).floor();
+        if unit < 0.0 || unit >= self.cells_dim as f64 {
+            return None;
+        }
+        Some(unit as usize)
+    }
+
+    fn cell_center(&self, unit: usize) -> f64 {
+        (unit as f64 + 0.5) * self.cell_size - self.dim / 2.0
+    }
+
+    /// Index and center of the cells closer than radius to the position
+    fn cells_near(&self, position: V2D, radius: f64) -> Vec<(usize, V2D)> {
+        let last = self.cells_dim - 1;
+        let min_x = self.cell_unit(position.x - radius).unwrap_or(0);
+        let min_y = self.cell_unit(position.y - radius).unwrap_or(0);
+        let max_x = self.cell_unit(position.x + radius).unwrap_or(last);
+        let max_y = self.cell_unit(position.y + radius).unwrap_or(last);
+        let mut cells = vec![];
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let center = V2D::new(self.cell_center(x), self.cell_center(y));
+                if center.distance(position) < radius {
+                    cells.push((y * self.cells_dim + x, center));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Adds to every cell closer than radius to the position, with a weight fading from 1 to 0
+    fn spread(&mut self, position: V2D, radius: f64, mut f: impl FnMut(&mut InfluenceCell, f64)) {
+        for (index, center) in self.cells_near(position, radius) {
+            let weight = 1.0 - center.distance(position) / radius;
+            f(&mut self.cells[index], weight);
+        }
+    }
+
+    /// Cell at a point, empty outside the map
+    pub fn at(&self, position: V2D) -> InfluenceCell {
+        self.cell_unit(position.x)
+            .zip(self.cell_unit(position.y))
+            .and_then(|(x, y)| self.cells.get(y * self.cells_dim + x))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Water cell within radius of the target with the least threat, the closest one on ties
+    pub fn staging_point(&self, game_state: &ServerState, target: V2D, radius: f64) -> Option<V2D> {
+        self.cells_near(target, radius)
+            .into_iter()
+            .filter(|(_, center)| {
+                game_state
+                    .game_map
+                    .get(center.x, center.y)
+                    .is_some_and(|tile| tile.is_nav_water())
+            })
+            .min_by(|(a, a_center), (b, b_center)| {
+                let a_distance = a_center.distance(target);
+                let b_distance = b_center.distance(target);
+                self.cells[*a]
+                    .threat
+                    .total_cmp(&self.cells[*b].threat)
+                    .then(a_distance.total_cmp(&b_distance))
+            })
+            .map(|(_, center)| center)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::InfluenceMap;
+    use crate::utils::vectors::V2D;
+
+    #[test]
+    fn test_spread_fades_with_distance() {
+        let mut map = InfluenceMap::new(1, 1000.0, 50.0);
+        map.spread(V2D::new(0.0, 0.0), 200.0, |cell, weight| {
+            cell.enemy += weight
+        });
+        let center = map.at(V2D::new(10.0, 10.0)).enemy;
+        let near = map.at(V2D::new(120.0, 0.0)).enemy;
+        let far = map.at(V2D::new(400.0, 0.0)).enemy;
+        assert!(center > near);
+        assert!(near > 0.0);
+        assert_eq!(far, 0.0);
+        assert_eq!(map.at(V2D::new(5000.0, 0.0)).enemy, 0.0);
+    }
+}
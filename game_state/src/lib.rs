@@ -1,19 +1,33 @@
 mod boidlike;
+mod bot_brain;
 mod bot_player;
 mod bullet;
+mod currents;
+mod formation;
+mod fort;
 mod game_map;
 mod game_noise;
 mod hashgrid;
+mod influence;
 mod island;
+mod loot;
+mod match_state;
+mod navigation;
+mod pirates;
 mod player;
 mod player_state;
 mod server;
 mod server_state;
 mod ship;
+mod storm;
+mod trade;
 mod utils;
 mod world_gen;
+pub use bot_brain::BotBrainKind;
+pub use bot_player::{BotConfig, BotDifficulty, BotPersonality};
+pub use match_state::MatchRules;
 pub use player_state::PlayerState;
-pub use server::game_server::{DBStatsMessage, GameServer, TICK_TIME};
+pub use server::game_server::{DBStatsMessage, GameServer, MatchResult, ServerConfig, TICK_TIME};
 use std::sync::OnceLock;
 #[cfg(target_arch = "wasm32")]
 mod wasm_game;
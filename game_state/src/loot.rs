@@ -0,0 +1,92 @@
+use crate::utils::vectors::V2D;
+use cgmath::MetricSpace;
+use serde::{Deserialize, Serialize};
+
+const WRECK_TTL: f64 = 30.0;
+const CRATE_TTL: f64 = 120.0;
+pub const MAX_CRATES: usize = 10;
+pub const CRATE_SPAWN_CHANCE_PER_SECOND: f64 = 0.1;
+/// Crates spawn inside this fraction of the map
+pub const CRATE_SPAWN_AREA: f64 = 0.9;
+/// Ships closer than this to the loot collect it
+pub const LOOT_PICKUP_RADIUS: f64 = 10.0;
+pub const REPAIR_HP: f64 = 30.0;
+/// Seconds the faster reload lasts
+pub const FAST_RELOAD_DURATION: f64 = 30.0;
+pub const RESOURCES_REWARD: usize = 10;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum LootKind {
+    /// Debris left by a sunk ship
+    Wreck,
+    /// Supply crate spawned at random on open water
+    Crate,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Reward {
+    Repair,
+    FastReload,
+    Resources,
+}
+
+impl Reward {
+    pub fn random(rng: &mut fastrand::Rng) -> Self {
+        match rng.u8(..3) {
+            0 => Reward::Repair,
+            1 => Reward::FastReload,
+            _ => Reward::Resources,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Loot {
+    pub id: u64,
+    pub position: V2D,
+    pub kind: LootKind,
+    pub reward: Reward,
+    pub time_created: f64,
+}
+
+impl Loot {
+    pub fn ttl(&self) -> f64 {
+        match self.kind {
+            LootKind::Wreck => WRECK_TTL,
+            LootKind::Crate => CRATE_TTL,
+        }
+    }
+
+    pub fn is_over(&self, current_time: f64) -> bool {
+        current_time - self.time_created > self.ttl()
+    }
+
+    pub fn can_be_picked_from(&self, position: V2D) -> bool {
+        self.position.distance(position) < LOOT_PICKUP_RADIUS
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Loot, LootKind, Reward};
+    use crate::utils::vectors::V2D;
+
+    #[test]
+    fn test_loot_expires() {
+        let wreck = Loot {
+            id: 1,
+            position: V2D::new(0.0, 0.0),
+            kind: LootKind::Wreck,
+            reward: Reward::Repair,
+            time_created: 0.0,
+        };
+        let supply = Loot {
+            kind: LootKind::Crate,
+            ..wreck.clone()
+        };
+        assert!(wreck.is_over(60.0));
+        assert!(!supply.is_over(60.0));
+        assert!(wreck.can_be_picked_from(V2D::new(5.0, 0.0)));
+        assert!(!wreck.can_be_picked_from(V2D::new(20.0, 0.0)));
+    }
+}
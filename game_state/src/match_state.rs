@@ -0,0 +1,163 @@
+use crate::player_state::PlayerState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Seconds after the round start before we look for a last player standing,
+/// the starting ships are still being created before that.
+const LAST_PLAYER_STANDING_GRACE: f64 = 30.0;
+
+/// How a round is won. With the default rules the round never ends.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MatchRules {
+    /// Seconds until the round ends, the player with the biggest part of the map wins
+    pub time_limit: Option<f64>,
+    /// Percentage of the map a player must own to win right away
+    pub map_control_to_win: Option<f64>,
+    /// The round ends when only one player still has ships or islands
+    pub last_player_standing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MatchPhase {
+    Running,
+    Ended { winner: Option<u64>, end_time: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatchState {
+    pub rules: MatchRules,
+    pub phase: MatchPhase,
+    pub round: u32,
+    pub round_start: f64,
+}
+
+/// What the clients need to show the match status
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchInfo {
+    pub round: u32,
+    pub running: bool,
+    pub remaining_time: Option<f64>,
+    pub winner: Option<u64>,
+}
+
+impl Default for MatchState {
+    fn default() -> Self {
+        Self {
+            rules: MatchRules::default(),
+            phase: MatchPhase::Running,
+            round: 0,
+            round_start: 0.0,
+        }
+    }
+}
+
+impl MatchState {
+    pub fn is_running(&self) -> bool {
+        self.phase == MatchPhase::Running
+    }
+
+    pub fn remaining_time(&self, current_time: f64) -> Option<f64> {
+        let time_limit = self.rules.time_limit?;
+        let elapsed = current_time - self.round_start;
+        Some((time_limit - elapsed).max(0.0))
+    }
+
+    pub fn info(&self, current_time: f64) -> MatchInfo {
+        let (running, winner, time) = match self.phase {
+            MatchPhase::Running => (true, None, current_time),
+            MatchPhase::Ended { winner, end_time } => (false, winner, end_time),
+        };
+        MatchInfo {
+            round: self.round,
+            running,
+            remaining_time: self.remaining_time(time),
+            winner,
+        }
+    }
+
+    /// Returns Some with the winner if the round should end now.
+    /// The winner is None when nobody is left.
+    pub fn check_end(
+        &self,
+        players: &BTreeMap<u64, PlayerState>,
+        current_time: f64,
+    ) -> Option<Option<u64>> {
+        if !self.is_running() {
+            return None;
+        }
+        if let Some(map_control) = self.rules.map_control_to_win {
+            let winner = players
+                .values()
+                .find(|player| player.percentage_of_map >= map_control);
+            if let Some(winner) = winner {
+                return Some(Some(winner.id));
+            }
+        }
+        let elapsed = current_time - self.round_start;
+        if self.rules.last_player_standing && elapsed > LAST_PLAYER_STANDING_GRACE {
+            let alive: Vec<_> = players
+                .values()
+                .filter(|player| player.ships > 0 || player.islands > 0)
+                .collect();
+            if players.len() > 1 && alive.len() <= 1 {
+                return Some(alive.first().map(|player| player.id));
+            }
+        }
+        if self.remaining_time(current_time) == Some(0.0) {
+            return Some(leader(players));
+        }
+        None
+    }
+}
+
+/// Player with the biggest part of the map, kills break ties
+fn leader(players: &BTreeMap<u64, PlayerState>) -> Option<u64> {
+    players
+        .values()
+        .max_by(|a, b| {
+            a.percentage_of_map
+                .total_cmp(&b.percentage_of_map)
+                .then(a.kills.cmp(&b.kills))
+        })
+        .map(|player| player.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MatchRules, MatchState};
+    use crate::player_state::PlayerState;
+    use std::collections::BTreeMap;
+
+    fn players() -> BTreeMap<u64, PlayerState> {
+        let mut players = BTreeMap::new();
+        for id in 1..=3 {
+            let mut player = PlayerState::new(format!("p{id}"), id, "br".to_string());
+            player.percentage_of_map = id as f64 * 10.0;
+            player.ships = 1;
+            players.insert(id, player);
+        }
+        players
+    }
+
+    #[test]
+    fn test_match_end() {
+        let mut state = MatchState::default();
+        let mut players = players();
+        assert_eq!(state.check_end(&players, 1000.0), None);
+
+        state.rules = MatchRules {
+            time_limit: Some(100.0),
+            map_control_to_win: Some(25.0),
+            last_player_standing: true,
+        };
+        assert_eq!(state.check_end(&players, 10.0), Some(Some(3)));
+
+        state.rules.map_control_to_win = None;
+        assert_eq!(state.check_end(&players, 50.0), None);
+        assert_eq!(state.check_end(&players, 100.0), Some(Some(3)));
+
+        players.get_mut(&1).unwrap().ships = 0;
+        players.get_mut(&3).unwrap().ships = 0;
+        assert_eq!(state.check_end(&players, 50.0), Some(Some(2)));
+    }
+}
@@ -0,0 +1,304 @@
+use cgmath::{InnerSpace, MetricSpace};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bullet::AmmoKind,
+    server_state::ServerState,
+    ship::{ShipKey, ShipState, DECELERATION},
+    utils::vectors::V2D,
+};
+
+pub const BOAT_SPEED: f64 = 16.0;
+/// Slowest a ship sails to wait for the rest of its group
+const MIN_GROUP_SPEED_FACTOR: f64 = 0.3;
+const STORM_MARGIN: f64 = 50.0;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum OrderKind {
+    #[default]
+    Move,
+    /// Goes back to the end of the queue once reached, so the ship keeps sailing the loop
+    Patrol,
+    /// Stops to fight the enemies in range on the way, then goes on
+    AttackMove,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ShipOrder {
+    pub kind: OrderKind,
+    pub target: V2D,
+    /// Loaded when the ship fights during an attack move
+    pub ammo: AmmoKind,
+}
+
+/// Waypoints a ship is sailing through, the state steers the ship along them every tick
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShipPath {
+    pub waypoints: Vec<V2D>,
+    /// Part of BOAT_SPEED the ship sails at, so a group arrives together
+    pub speed_factor: f64,
+    /// The order the waypoints lead to first, then the queued ones
+    pub orders: Vec<ShipOrder>,
+    pub avoid_storms: bool,
+}
+
+impl ShipPath {
+    /// Where the ship ends up once the queue is done
+    pub fn last_target(&self) -> Option<V2D> {
+        self.orders.last().map(|order| order.target)
+    }
+
+    /// Adds the order after the others. A patrol needs a point to come back to,
+    /// so the end of the queue before it becomes a patrol point too.
+    pub fn queue(&mut self, order: ShipOrder) {
+        if order.kind == OrderKind::Patrol {
+            if let Some(&last) = self
+                .orders
+                .last()
+                .filter(|last| last.kind != OrderKind::Patrol)
+            {
+                self.orders.push(ShipOrder {
+                    kind: OrderKind::Patrol,
+                    ..last
+                });
+            }
+        }
+        self.orders.push(order);
+    }
+
+    /// Drops the order that was reached, a patrol goes back at the end of the queue.
+    /// Returns the next target, if any.
+    pub fn next_order(&mut self) -> Option<V2D> {
+        if self.orders.is_empty() {
+            return None;
+        }
+        let reached = self.orders.remove(0);
+        if reached.kind == OrderKind::Patrol && !self.orders.is_empty() {
+            self.orders.push(reached);
+        }
+        self.orders.first().map(|order| order.target)
+    }
+
+    pub fn current_order(&self) -> Option<&ShipOrder> {
+        self.orders.first()
+    }
+
+    /// Velocity towards the next waypoint, None once the ship reached the last one
+    pub fn steer(&mut self, ship: &ShipState) -> Option<V2D> {
+        loop {
+            let next = *self.waypoints.first()?;
+            let direction = next - ship.position;
+            let is_final_target = self.waypoints.len() == 1;
+            let error_tolerance = if is_final_target {
+                1.0
+            } else {
+                turn_anticipation(ship, next, self.waypoints[1]).max(5.0)
+            };
+            if direction.magnitude() < error_tolerance {
+                self.waypoints.remove(0);
+                continue;
+            }
+            let cruise_speed = BOAT_SPEED * self.speed_factor;
+            let speed_module = if is_final_target {
+                arrival_speed(direction.magnitude()).min(cruise_speed)
+            } else {
+                cruise_speed
+            };
+            return Some(direction.normalize() * speed_module);
+        }
+    }
+}
+
+/// Paths from each ship to the target of its order. Ships with shorter trips get a lower
+/// speed_factor, so the group arrives together. Each ship sails its own path to its slot,
+/// the formation only takes shape at the target.
+/// A patrol also comes back to where the ship started.
+pub fn plan_paths(
+    game: &ServerState,
+    orders: &[(ShipKey, ShipOrder)],
+    avoid_storms: bool,
+) -> Vec<(ShipKey, ShipPath)> {
+    let paths: Vec<_> = orders
+        .iter()
+        .filter_map(|(key, order)| {
+            let ship = game.ship_collection.get(key)?;
+            let waypoints = plan_waypoints(game, ship.position, order.target, avoid_storms)?;
+            let mut orders = vec![*order];
+            if order.kind == OrderKind::Patrol {
+                orders.push(ShipOrder {
+                    target: ship.position,
+                    ..*order
+                });
+            }
+            Some((
+                *key,
+                path_length(ship.position, &waypoints),
+                waypoints,
+                orders,
+            ))
+        })
+        .collect();
+    let longest = paths
+        .iter()
+        .map(|(_, length, _, _)| *length)
+        .fold(0.0, f64::max);
+    paths
+        .into_iter()
+        .map(|(key, length, waypoints, orders)| {
+            let speed_factor = if longest > 0.0 {
+                (length / longest).max(MIN_GROUP_SPEED_FACTOR)
+            } else {
+                1.0
+            };
+            let path = ShipPath {
+                waypoints,
+                speed_factor,
+                orders,
+                avoid_storms,
+            };
+            (key, path)
+        })
+        .collect()
+}
+
+/// Waypoints from start to target, without the start
+pub fn plan_waypoints(
+    game: &ServerState,
+    start: V2D,
+    target: V2D,
+    avoid_storms: bool,
+) -> Option<Vec<V2D>> {
+    let path = game.game_map.find_path(start, target)?;
+    //the fist one is already the current position
+    let waypoints = path[1..].to_vec();
+    if avoid_storms {
+        return Some(detour_storms(game, start, waypoints));
+    }
+    Some(waypoints)
+}
+
+fn path_length(start: V2D, path: &[V2D]) -> f64 {
+    let mut previous = start;
+    let mut length = 0.0;
+    for &point in path {
+        length += previous.distance(point);
+        previous = point;
+    }
+    length
+}
+
+/// How far from the waypoint we should start turning to the next one,
+/// so the ship does not overshoot it.
+fn turn_anticipation(ship: &ShipState, waypoint: V2D, next: V2D) -> f64 {
+    let incoming = waypoint - ship.position;
+    let outgoing = next - waypoint;
+    if incoming.magnitude2() == 0.0 || outgoing.magnitude2() == 0.0 {
+        return 0.0;
+    }
+    let cos_turn = incoming.normalize().dot(outgoing.normalize());
+    ship.turn_radius() * (1.0 - cos_turn)
+}
+
+/// Speed that still lets the ship stop at the target with the available deceleration
+fn arrival_speed(distance: f64) -> f64 {
+    (2.0 * DECELERATION * distance).sqrt().min(BOAT_SPEED)
+}
+
+/// Adds a waypoint around each storm the path would cross.
+/// Storms move, so this is only a best effort at the time the order is given.
+fn detour_storms(game: &ServerState, start: V2D, path: Vec<V2D>) -> Vec<V2D> {
+    let mut result = Vec::with_capacity(path.len());
+    let mut previous = start;
+    for next in path {
+        let storm = game.storms.values().find(|storm| {
+            !storm.contains(previous) && storm.intersects_segment(previous, next, STORM_MARGIN)
+        });
+        if let Some(storm) = storm {
+            let closest = storm.closest_point_on_segment(previous, next);
+            let away = closest - storm.position;
+            let away = if away.magnitude2() > 0.0 {
+                away.normalize()
+            } else {
+                let direction = (next - previous).normalize();
+                V2D::new(-direction.y, direction.x)
+            };
+            let detour = storm.position + away * (storm.radius + STORM_MARGIN);
+            let map = &game.game_map;
+            if map.can_go_straight(&previous, &detour) && map.can_go_straight(&detour, &next) {
+                result.push(detour);
+            }
+        }
+        result.push(next);
+        previous = next;
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OrderKind, ShipOrder, ShipPath, BOAT_SPEED};
+    use crate::{bullet::AmmoKind, ship::ShipState, utils::vectors::V2D};
+
+    #[test]
+    fn test_steer_along_path() {
+        let mut ship = ShipState::default();
+        let mut path = ShipPath {
+            waypoints: vec![
+                V2D::new(0.5, 0.0),
+                V2D::new(100.0, 0.0),
+                V2D::new(100.0, 3.0),
+            ],
+            speed_factor: 0.5,
+            orders: vec![],
+            avoid_storms: false,
+        };
+        // the first waypoint is already reached
+        let speed = path.steer(&ship).unwrap();
+        assert_eq!(path.waypoints.len(), 2);
+        assert_eq!(speed, V2D::new(BOAT_SPEED * 0.5, 0.0));
+
+        // slows down for the last one
+        ship.position = V2D::new(100.0, 0.0);
+        let speed = path.steer(&ship).unwrap();
+        assert_eq!(path.waypoints.len(), 1);
+        assert!(speed.y > 0.0 && speed.y < BOAT_SPEED * 0.5);
+
+        ship.position = V2D::new(100.0, 2.5);
+        assert_eq!(path.steer(&ship), None);
+    }
+
+    #[test]
+    fn test_patrol_loops() {
+        let order = |kind, x| ShipOrder {
+            kind,
+            target: V2D::new(x, 0.0),
+            ammo: AmmoKind::default(),
+        };
+        let mut path = ShipPath {
+            waypoints: vec![],
+            speed_factor: 1.0,
+            orders: vec![order(OrderKind::Move, 10.0)],
+            avoid_storms: false,
+        };
+        // the patrol comes back to where the move ends
+        path.queue(order(OrderKind::Patrol, 20.0));
+        assert_eq!(path.orders.len(), 3);
+        assert_eq!(path.last_target(), Some(V2D::new(20.0, 0.0)));
+
+        let targets: Vec<_> = (0..5).map_while(|_| path.next_order()).collect();
+        assert_eq!(
+            targets.iter().map(|target| target.x).collect::<Vec<_>>(),
+            vec![10.0, 20.0, 10.0, 20.0, 10.0]
+        );
+        assert!(path.orders.iter().all(|o| o.kind == OrderKind::Patrol));
+
+        let mut path = ShipPath {
+            orders: vec![order(OrderKind::Move, 10.0)],
+            ..path
+        };
+        assert_eq!(path.next_order(), None);
+        assert!(path.orders.is_empty());
+    }
+}
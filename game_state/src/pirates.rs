@@ -0,0 +1,137 @@
+use crate::{player::Player, server_state::ServerState, utils::vectors::V2D};
+use cgmath::MetricSpace;
+
+/// Pirates have no PlayerState, this id only marks their ships.
+/// It fits in a f64 so the frontend can read it.
+pub const PIRATE_PLAYER_ID: u64 = u32::MAX as u64;
+/// Seconds between waves with intensity 1.0
+const PIRATE_WAVE_TIME: f64 = 60.0;
+const PIRATE_WAVE_SIZE: usize = 5;
+/// Pirate ships alive at the same time with intensity 1.0
+const MAX_PIRATE_SHIPS: f64 = 20.0;
+/// Enemy ships this close to a target count as its defenders
+const HUNT_RADIUS: f64 = 100.0;
+const TIME_FOR_ACTION: f64 = 2.0;
+/// Fraction of the map where the edge spawns happen
+const EDGE_SPAWN_AREA: f64 = 0.9;
+
+pub fn is_pirate(player_id: u64) -> bool {
+    player_id == PIRATE_PLAYER_ID
+}
+
+/// Neutral faction of server owned ships. Pirates show up in waves at small islands
+/// or at the edge of the map, hunt weakly defended fleets and raid undefended islands.
+pub struct Pirates {
+    pub player: Player,
+    pub intensity: f64,
+    next_wave_time: f64,
+    time_to_next_action: f64,
+}
+
+impl Pirates {
+    pub fn new(intensity: f64) -> Self {
+        let mut player = Player::new(PIRATE_PLAYER_ID);
+        player.avoid_storms = true;
+        Self {
+            player,
+            intensity,
+            next_wave_time: PIRATE_WAVE_TIME / intensity,
+            time_to_next_action: 0.0,
+        }
+    }
+
+    pub fn tick(&mut self, game_state: &ServerState) {
+        self.player.select_all(game_state);
+        self.player.auto_shoot(game_state);
+
+        let current_time = game_state.current_time;
+        if current_time >= self.next_wave_time {
+            self.next_wave_time = current_time + PIRATE_WAVE_TIME / self.intensity;
+            self.spawn_wave(game_state);
+        }
+
+        if current_time < self.time_to_next_action {
+            return;
+        }
+        self.time_to_next_action = current_time + TIME_FOR_ACTION;
+        if let Some(target) = self.find_target(game_state) {
+            self.player.select_all_idle(game_state);
+            self.player
+                .move_selected_ships(game_state, target.x, target.y);
+        }
+    }
+
+    fn spawn_wave(&mut self, game_state: &ServerState) {
+        let max_ships = (MAX_PIRATE_SHIPS * self.intensity) as usize;
+        let ships = self.player.number_of_ships(game_state);
+        if ships >= max_ships {
+            return;
+        }
+        let spawn = self.spawn_point(game_state);
+        for _ in 0..PIRATE_WAVE_SIZE.min(max_ships - ships) {
+            self.player.create_ship(spawn.x, spawn.y);
+        }
+    }
+
+    fn spawn_point(&mut self, game_state: &ServerState) -> V2D {
+        let small_islands = &game_state.game_map.small_islands;
+        if !small_islands.is_empty() && self.player.rng.bool() {
+            let index = self.player.rng.usize(..small_islands.len());
+            if let Some(island) = small_islands.values().nth(index) {
+                return island.center;
+            }
+        }
+        let edge = game_state.game_map.dim / 2.0 * EDGE_SPAWN_AREA;
+        let along = (self.player.rng.f64() * 2.0 - 1.0) * edge;
+        match self.player.rng.u8(..4) {
+            0 => V2D::new(-edge, along),
+            1 => V2D::new(edge, along),
+            2 => V2D::new(along, -edge),
+            _ => V2D::new(along, edge),
+        }
+    }
+
+    /// Weakest fleet we can beat, otherwise the closest island nobody is defending
+    fn find_target(&self, game_state: &ServerState) -> Option<V2D> {
+        let ships = self.player.my_ships(game_state);
+        if ships.is_empty() {
+            return None;
+        }
+        let mut center = V2D::new(0.0, 0.0);
+        for ship in ships.iter() {
+            center += ship.position;
+        }
+        center /= ships.len() as f64;
+
+        let weakest = game_state
+            .ship_collection
+            .values()
+            .filter(|ship| !is_pirate(ship.player_id) && !ship.is_sinking())
+            .map(|ship| {
+                let defenders = game_state
+                    .hash_grid
+                    .query_near(ship.position, HUNT_RADIUS)
+                    .filter_map(|entity| entity.as_boat())
+                    .filter(|(key, _)| game_state.are_allies(key.player_id, ship.player_id))
+                    .count();
+                (defenders, ship.position)
+            })
+            .min_by(|(a_defenders, a), (b_defenders, b)| {
+                a_defenders
+                    .cmp(b_defenders)
+                    .then(center.distance(*a).total_cmp(&center.distance(*b)))
+            });
+        if let Some((defenders, position)) = weakest {
+            if defenders < ships.len() {
+                return Some(position);
+            }
+        }
+
+        game_state
+            .island_dynamic
+            .values()
+            .filter(|island| island.owner.is_some() && island.contesting.is_empty())
+            .map(|island| island.lighthouse)
+            .min_by(|a, b| center.distance(*a).total_cmp(&center.distance(*b)))
+    }
+}
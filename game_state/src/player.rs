@@ -1,37 +1,43 @@
 use crate::{
-    bullet::Bullet,
+    bullet::{AmmoKind, Bullet},
+    formation::{assign_slots, Formation},
     hashgrid::HashEntityKind,
+    navigation::{OrderKind, ShipOrder},
     server_state::{ServerState, StateMessage},
-    ship::{ShipKey, ShipState},
-    utils::{spiral_search::SpiralSearch, vectors::V2D},
+    ship::{ShipKey, ShipState, SHIP_MAX_HP},
+    utils::vectors::V2D,
 };
 use anyhow::Context;
-use cgmath::InnerSpace;
+use cgmath::{InnerSpace, MetricSpace};
 use log::error;
 use std::{
     collections::HashMap,
     sync::mpsc::{Receiver, Sender},
 };
 
-const BOAT_SPEED: f64 = 16.0;
-
-#[derive(Debug)]
-pub struct PlayerShip {
-    path: Vec<V2D>,
-    id: u64,
-    should_remove: bool,
-    target: Option<V2D>,
-    speed: Option<V2D>,
-}
+/// Chance of each ready ship opening fire on a tick of auto_shoot
+const AUTO_SHOOT_CHANCE: f32 = 0.1;
+/// Target score added for each other ship of ours that can reach the target
+const FOCUS_FIRE_WEIGHT: f64 = 0.2;
+/// Target score added to enemies close to our islands
+const ISLAND_THREAT_WEIGHT: f64 = 1.0;
+const ISLAND_THREAT_RADIUS: f64 = 100.0;
+/// Target score removed when another ship of ours already shot at the target this tick
+const SHOT_ALREADY_PENALTY: f64 = 0.5;
 
 pub struct Player {
     pub id: u64,
-    moving_ships: HashMap<u64, PlayerShip>,
     pub selected_ships: Vec<u64>,
     actions: Sender<StateMessage>,
     actions_buffer: Receiver<StateMessage>,
     pub rng: fastrand::Rng,
     pub shoot_radius: f64,
+    /// Make paths go around storms
+    pub avoid_storms: bool,
+    /// Ammunition loaded in every shot this player fires
+    pub ammo: AmmoKind,
+    /// Shape the selected ships take when they are moved together
+    pub formation: Formation,
 }
 
 impl Player {
@@ -39,12 +45,14 @@ impl Player {
         let (sender, receiver) = std::sync::mpsc::channel();
         Player {
             id,
-            moving_ships: HashMap::new(),
             actions: sender,
             actions_buffer: receiver,
             selected_ships: Vec::new(),
             rng: fastrand::Rng::with_seed(id),
             shoot_radius: 10.0,
+            avoid_storms: false,
+            ammo: AmmoKind::default(),
+            formation: Formation::default(),
         }
     }
 
@@ -52,22 +60,73 @@ impl Player {
         game_state
             .ship_collection
             .values()
-            .filter(|ship| ship.player_id == self.id)
+            .filter(|ship| ship.player_id == self.id && !ship.is_merchant() && !ship.is_sinking())
             .collect()
     }
 
+    /// Sends the selection to the formation slots around the target,
+    /// the state finds the paths and sails the ships along them.
     pub fn move_selected_ships(&mut self, game_state: &ServerState, x: f64, y: f64) {
-        let formation = unit_spiral_formation(self.selected_ships.len(), x, y, game_state);
-        self.selected_ships
-            .clone()
+        self.order_selected_ships(game_state, x, y, OrderKind::Move, false);
+    }
+
+    /// Gives the selection an order around the target. With append the ships carry it out
+    /// after the orders they already have, and the formation is laid out from where they
+    /// will be by then.
+    pub fn order_selected_ships(
+        &mut self,
+        game_state: &ServerState,
+        x: f64,
+        y: f64,
+        kind: OrderKind,
+        append: bool,
+    ) {
+        let ships: Vec<_> = self
+            .selected_ships
             .iter()
-            .zip(formation)
-            .for_each(|(&ship_id, (x, y))| {
-                let path = self.find_path_for_ship(game_state, ship_id, x, y);
-                if let Some(path) = path {
-                    self.make_ship_follow_path(ship_id, path);
-                }
-            });
+            .filter_map(|&id| game_state.get_ship(id, self.id))
+            .map(|ship| {
+                let queued = game_state
+                    .ship_paths
+                    .get(&ship.key())
+                    .and_then(|path| path.last_target())
+                    .filter(|_| append);
+                (ship.id, queued.unwrap_or(ship.position))
+            })
+            .collect();
+        if ships.is_empty() {
+            return;
+        }
+        let target = V2D::new(x, y);
+        let positions: Vec<V2D> = ships.iter().map(|(_, position)| *position).collect();
+        let center =
+            positions.iter().fold(V2D::new(0.0, 0.0), |sum, &p| sum + p) / positions.len() as f64;
+        let direction = target - center;
+        let slots = self
+            .formation
+            .slots(ships.len(), target, direction, game_state);
+        let assignment = assign_slots(&positions, &slots, direction);
+        let orders = ships
+            .iter()
+            .zip(assignment)
+            .map(|(&(ship_id, _), slot)| {
+                let order = ShipOrder {
+                    kind,
+                    target: slots[slot],
+                    ammo: self.ammo,
+                };
+                (ship_id, order)
+            })
+            .collect();
+        let msg = StateMessage::MoveShipsTo {
+            player_id: self.id,
+            orders,
+            avoid_storms: self.avoid_storms,
+            append,
+        };
+        if let Err(err) = self.actions.send(msg).context(file!()) {
+            error!("Error sending message: {}", err)
+        };
     }
 
     pub fn change_shoot_radius(&mut self, r: f64) {
@@ -84,37 +143,6 @@ impl Player {
             .retain(|&id| game.get_ship(id, self.id).is_some())
     }
 
-    fn make_ship_follow_path(&mut self, ship_id: u64, path: Vec<V2D>) {
-        let ship = PlayerShip {
-            path,
-            id: ship_id,
-            should_remove: false,
-            target: None,
-            speed: None,
-        };
-
-        self.moving_ships.insert(ship_id, ship);
-    }
-
-    pub fn find_path_for_ship(
-        &mut self,
-        game_state: &ServerState,
-        ship_id: u64,
-        x: f64,
-        y: f64,
-    ) -> Option<Vec<V2D>> {
-        let server_ship = game_state
-            .ship_collection
-            .get(&ShipKey::new(ship_id, self.id))?;
-        let path = game_state
-            .game_map
-            .find_path(server_ship.position, (x, y))?;
-
-        //the fist one is already the current position
-        let path = path[1..].to_vec();
-        return Some(path);
-    }
-
     pub fn shoot_at_with(&mut self, ship_id: u64, x: f64, y: f64) {
         let theta = self.rng.f64() * std::f64::consts::PI * 2.0;
         let r = self.rng.f64().sqrt() * self.shoot_radius;
@@ -127,6 +155,7 @@ impl Player {
             ship_id,
             player_id: self.id,
             target: (x, y).into(),
+            ammo: self.ammo,
         };
         if let Err(err) = self.actions.send(msg).context(file!()) {
             error!("Error sending message: {}", err)
@@ -151,53 +180,111 @@ impl Player {
     }
 
     pub fn select_all_idle(&mut self, game_state: &ServerState) {
-        self.selected_ships = self
-            .player_ships(game_state)
-            .filter_map(|ship| {
-                if self.moving_ships.contains_key(&ship.id) {
-                    return None;
-                }
-                return Some(ship.id);
-            })
-            .collect();
+        self.selected_ships = self.idle_ships(game_state);
     }
 
+    /// Ships not following a path
+    pub fn idle_ships(&self, game_state: &ServerState) -> Vec<u64> {
+        self.player_ships(game_state)
+            .filter(|ship| !game_state.ship_paths.contains_key(&ship.key()))
+            .map(|ship| ship.id)
+            .collect()
+    }
+
+    /// Each ready ship picks the enemy with the best score among the ones it can hit
+    /// and aims where the enemy will be when the bullet lands.
     pub fn auto_shoot(&mut self, game_state: &ServerState) {
         let mut shot_already = vec![];
         let mut rng = self.rng.clone();
-        let pairs = self
+        let seen_storms = game_state.storms_seen_by(self.id);
+        let shooters = self
             .shooting_ships(game_state)
-            .filter_map(|ship| {
-                let enemies = game_state
-                    .hash_grid
-                    .query_near(ship.position.into(), Bullet::max_distance())
-                    .filter_map(|entity| {
-                        if let HashEntityKind::Boat(key) = entity.entity {
-                            if key.player_id != self.id {
-                                return Some((entity.position, key));
-                            } else {
-                                return None;
-                            }
-                        } else {
-                            return None;
-                        }
-                    });
-                for (enemy_pos, key) in enemies {
-                    if shot_already.contains(&key) || rng.f32() > 0.1 {
-                        continue;
-                    }
-                    shot_already.push(key);
-                    return Some((ship.id, enemy_pos));
-                }
-                return None;
-            })
+            .map(|ship| (ship, self.enemies_in_range(game_state, ship, &seen_storms)))
             .collect::<Vec<_>>();
-        for (id, enemy) in pairs {
-            self.shoot_at_with(id, enemy.x, enemy.y);
+
+        let mut shooters_in_range: HashMap<ShipKey, usize> = HashMap::new();
+        for enemy in shooters.iter().flat_map(|(_, enemies)| enemies) {
+            *shooters_in_range.entry(enemy.key()).or_insert(0) += 1;
+        }
+        let scores = shooters_in_range
+            .iter()
+            .filter_map(|(key, shooters)| {
+                let enemy = game_state.ship_collection.get(key)?;
+                Some((*key, self.target_score(game_state, enemy, *shooters)))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut pairs = vec![];
+        for (ship, enemies) in shooters.iter() {
+            if enemies.is_empty() || rng.f32() > AUTO_SHOOT_CHANCE {
+                continue;
+            }
+            let best = enemies
+                .iter()
+                .filter_map(|enemy| {
+                    let key = enemy.key();
+                    let aim =
+                        Bullet::intercept(ship.position, enemy.position, enemy.speed, self.ammo)?;
+                    let mut score = scores.get(&key).copied().unwrap_or(0.0);
+                    if shot_already.contains(&key) {
+                        score -= SHOT_ALREADY_PENALTY;
+                    }
+                    Some((score, key, aim))
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((_, key, aim)) = best {
+                shot_already.push(key);
+                pairs.push((ship.id, aim));
+            }
+        }
+        for (id, aim) in pairs {
+            self.shoot_at_with(id, aim.x, aim.y);
         }
         self.rng = rng;
     }
 
+    fn enemies_in_range<'a>(
+        &self,
+        game_state: &'a ServerState,
+        ship: &ShipState,
+        seen_storms: &[u64],
+    ) -> Vec<&'a ShipState> {
+        game_state
+            .hash_grid
+            .query_near(ship.position, self.ammo.max_distance())
+            .filter_map(|entity| {
+                let key = match entity.entity {
+                    HashEntityKind::Boat(key) => key,
+                    _ => return None,
+                };
+                let is_hidden = game_state.is_hidden_by_storm(entity.position, seen_storms);
+                let is_ally = game_state.are_allies(self.id, key.player_id);
+                if is_ally || is_hidden {
+                    return None;
+                }
+                game_state.ship_collection.get(&key)
+            })
+            .collect()
+    }
+
+    /// Damaged ships, ships many of ours can reach and ships close to our islands come first
+    fn target_score(&self, game_state: &ServerState, enemy: &ShipState, shooters: usize) -> f64 {
+        let damage = 1.0 - enemy.hp / SHIP_MAX_HP;
+        let focus = shooters.saturating_sub(1) as f64 * FOCUS_FIRE_WEIGHT;
+        let threatens_island = game_state.island_dynamic.values().any(|island| {
+            let is_ours = island
+                .owner
+                .is_some_and(|owner| game_state.are_allies(self.id, owner));
+            is_ours && island.lighthouse.distance(enemy.position) < ISLAND_THREAT_RADIUS
+        });
+        let threat = if threatens_island {
+            ISLAND_THREAT_WEIGHT
+        } else {
+            0.0
+        };
+        damage + focus + threat
+    }
+
     fn shooting_ships<'a>(&'a self, game: &'a ServerState) -> impl Iterator<Item = &'a ShipState> {
         self.player_ships(game).filter(|ship| {
             let is_selected = self.selected_ships.contains(&ship.id);
@@ -208,7 +295,7 @@ impl Player {
 
     pub fn can_shoot_here(&self, target: V2D, game: &ServerState) -> bool {
         let mut ships = self.shooting_ships(game).filter_map(|ship| {
-            Bullet::maybe_from_target(ship.position.into(), target)?;
+            Bullet::maybe_from_target_with(ship.position.into(), target, self.ammo)?;
             return Some(());
         });
         return ships.next().is_some();
@@ -218,7 +305,7 @@ impl Player {
         let id = self.id;
         game.ship_collection
             .values()
-            .filter(move |ship| ship.player_id == id)
+            .filter(move |ship| ship.player_id == id && !ship.is_merchant() && !ship.is_sinking())
     }
 
     pub fn create_ship(&mut self, x: f64, y: f64) {
@@ -234,6 +321,27 @@ impl Player {
         };
     }
 
+    pub fn create_trade_route(&mut self, from: u64, to: u64) {
+        let msg = StateMessage::CreateTradeRoute {
+            player_id: self.id,
+            from,
+            to,
+        };
+        if let Err(err) = self.actions.send(msg).context(file!()) {
+            error!("Error sending message: {}", err)
+        };
+    }
+
+    pub fn build_fort(&mut self, island_id: u64) {
+        let msg = StateMessage::BuildFort {
+            island_id,
+            player_id: self.id,
+        };
+        if let Err(err) = self.actions.send(msg).context(file!()) {
+            error!("Error sending message: {}", err)
+        };
+    }
+
     pub fn next_message(&self) -> Option<StateMessage> {
         self.actions_buffer.try_recv().ok()
     }
@@ -252,92 +360,6 @@ impl Player {
             .filter(|ship| ship.speed.magnitude2() == 0.0)
             .count();
     }
-
-    pub fn tick(&mut self, game_state: &ServerState) {
-        for player_ship in self.moving_ships.values_mut() {
-            let path = &mut player_ship.path;
-            let ship = game_state
-                .ship_collection
-                .get(&ShipKey::new(player_ship.id, self.id));
-            let ship = if let Some(ship) = ship {
-                ship
-            } else {
-                player_ship.should_remove = true;
-                continue;
-            };
-            loop {
-                if let Some(&next) = path.first() {
-                    let position: V2D = ship.position.into();
-                    let direction = next - position;
-                    let is_final_target = path.len() == 1;
-                    let error_tolerance = if is_final_target { 1.0 } else { 5.0 };
-                    let has_reached_goal = direction.magnitude() < error_tolerance;
-                    if has_reached_goal {
-                        path.remove(0);
-                        if path.is_empty() {
-                            if let Err(e) = self.actions.send(StateMessage::MoveShip {
-                                player_id: self.id,
-                                id: ship.id,
-                                speed: (0.0, 0.0).into(),
-                            }) {
-                                log::error!("Error sending message: {:?}", e)
-                            }
-                            player_ship.should_remove = true;
-                            break;
-                        } else {
-                            continue;
-                        }
-                    };
-                    player_ship.target = Some(next);
-                    let speed = direction.normalize() * BOAT_SPEED;
-                    let has_speed_chanded = player_ship
-                        .speed
-                        .map(|s| is_different(s, speed))
-                        .unwrap_or(true);
-
-                    if !has_speed_chanded {
-                        break;
-                    }
-
-                    player_ship.speed = Some(speed);
-
-                    if let Err(e) = self.actions.send(StateMessage::MoveShip {
-                        player_id: self.id,
-                        id: ship.id,
-                        speed: speed.into(),
-                    }) {
-                        log::error!("Error sending message: {:?}", e)
-                    }
-                    break;
-                } else {
-                    break;
-                }
-            }
-        }
-
-        self.moving_ships.retain(|_, ship| !ship.should_remove);
-    }
-}
-
-fn is_different(a: V2D, b: V2D) -> bool {
-    return (a - b).magnitude2() > 0.1;
-}
-
-fn unit_spiral_formation(n: usize, x: f64, y: f64, game: &ServerState) -> Vec<(f64, f64)> {
-    let cell_size = 20.0;
-    let spiral = SpiralSearch::new((0, 0));
-    let mut v = Vec::with_capacity(n);
-    for (x_i, y_i) in spiral {
-        let x = x_i as f64 * cell_size + x;
-        let y = y_i as f64 * cell_size + y;
-        if game.game_map.is_allowed_place(x, y) {
-            v.push((x, y));
-        }
-        if v.len() >= n {
-            break;
-        }
-    }
-    return v;
 }
 
 #[cfg(test)]
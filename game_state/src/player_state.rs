@@ -12,6 +12,34 @@ pub struct PlayerState {
     pub kills: usize,
     pub deaths: usize,
     pub flag: String,
+    pub team: Option<u8>,
+    /// Collected from loot and trade, spent on forts
+    pub resources: usize,
+    /// Most ships the player can have, island production stops once it is reached
+    pub ship_cap: usize,
+}
+
+/// Stats of all the players in a team added together
+#[derive(Debug, Clone, Serialize, PartialEq, Default)]
+pub struct TeamStats {
+    pub team: u8,
+    pub players: usize,
+    pub percentage_of_map: f64,
+    pub islands: usize,
+    pub ships: usize,
+    pub kills: usize,
+    pub deaths: usize,
+}
+
+impl TeamStats {
+    pub fn add(&mut self, player: &PlayerState) {
+        self.players += 1;
+        self.percentage_of_map += player.percentage_of_map;
+        self.islands += player.islands;
+        self.ships += player.ships;
+        self.kills += player.kills;
+        self.deaths += player.deaths;
+    }
 }
 
 impl Default for PlayerState {
@@ -25,6 +53,9 @@ impl Default for PlayerState {
             kills: 0,
             flag: PlayerState::get_player_flag(0),
             deaths: 0,
+            team: None,
+            resources: 0,
+            ship_cap: 0,
         }
     }
 }
@@ -44,4 +75,12 @@ impl PlayerState {
             ..Default::default()
         }
     }
+
+    /// A player is always an ally of itself, and of everyone on the same team
+    pub fn is_ally_of(&self, other: &PlayerState) -> bool {
+        if self.id == other.id {
+            return true;
+        }
+        self.team.is_some() && self.team == other.team
+    }
 }
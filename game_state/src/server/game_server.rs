@@ -1,6 +1,9 @@
 use crate::{
-    bot_player::BotPlayer,
-    server_state::{ServerState, StateMessage, PLAYER_START_SHIPS},
+    bot_brain::BotBrain,
+    bot_player::{BotConfig, BotPlayer},
+    match_state::{MatchPhase, MatchRules},
+    pirates::Pirates,
+    server_state::{GameConstants, ServerState, StateMessage, PLAYER_START_SHIPS},
     ship::ShipState,
     utils::vectors::V2D,
     PlayerState,
@@ -14,12 +17,14 @@ const MAX_BOTS: usize = 10;
 const SYNC_EVERY_N_FRAMES: u64 = 1000;
 pub const TICK_TIME: f64 = 1.0 / 60.0;
 const MAX_DOWN_TIME: u64 = 10_000;
+/// Seconds between the end of a round and the start of the next one
+const ROUND_RESTART_TIME: f64 = 10.0;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum GameMessage {
     FrameMessage(Vec<StateMessage>),
     InputMessage(StateMessage),
-    AddBot,
+    AddBot(Option<BotConfig>),
     AddBotShipAt(f64, f64),
     RemoveBot,
     PlayerCreated { x: f64, y: f64, id: u64 },
@@ -28,6 +33,7 @@ pub enum GameMessage {
     Ping(u64),
     Pong,
     Reconnection,
+    MatchEnded { winner: Option<u64>, round: u32 },
     None,
 }
 
@@ -54,8 +60,50 @@ struct PlayerBufferSenderPair {
     connection_down_time: Option<u64>,
 }
 
+/// How a server is set up when it is created
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub match_rules: MatchRules,
+    /// Number of teams the players are split into, 0 means free for all
+    pub teams: u8,
+    pub friendly_fire: bool,
+    /// How often pirates show up and how many of them, 0 means no pirates
+    pub pirate_intensity: f64,
+    /// Ships a player can have before owning any island
+    pub ship_cap: usize,
+    pub ship_cap_per_island: usize,
+    /// Fleets larger than this slow down island production, None disables upkeep
+    pub upkeep_free_ships: Option<usize>,
+    /// Used by the bots the server adds on its own and by AddBot without a config
+    pub bots: BotConfig,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            match_rules: MatchRules::default(),
+            teams: 0,
+            friendly_fire: true,
+            pirate_intensity: 0.0,
+            ship_cap: 100,
+            ship_cap_per_island: 0,
+            upkeep_free_ships: None,
+            bots: BotConfig::default(),
+        }
+    }
+}
+
+pub struct MatchResult {
+    pub server: String,
+    pub round: u32,
+    pub seed: u32,
+    pub winner: Option<PlayerState>,
+    pub players: Vec<PlayerState>,
+}
+
 pub enum DBStatsMessage {
     PlayerUpdate(PlayerState),
+    MatchResult(Box<MatchResult>),
 }
 
 pub struct GameServer {
@@ -69,6 +117,12 @@ pub struct GameServer {
     pub name: String,
     pub seed: u32,
     db_sender: Option<Sender<DBStatsMessage>>,
+    reported_round: Option<u32>,
+    teams: u8,
+    pirates: Option<Pirates>,
+    bot_config: BotConfig,
+    /// Keeps ticking with no human connected, for matches with only bots
+    pub headless: bool,
 }
 
 impl GameServer {
@@ -84,7 +138,60 @@ impl GameServer {
             name: "default".to_string(),
             db_sender,
             seed,
+            reported_round: None,
+            teams: 0,
+            pirates: None,
+            bot_config: BotConfig::default(),
+            headless: false,
+        }
+    }
+
+    pub fn set_config(&mut self, config: ServerConfig) {
+        self.teams = config.teams;
+        self.bot_config = config.bots;
+        self.pirates = if config.pirate_intensity > 0.0 {
+            Some(Pirates::new(config.pirate_intensity))
+        } else {
+            None
+        };
+        let constants = GameConstants {
+            friendly_fire: config.friendly_fire,
+            ship_cap: config.ship_cap,
+            ship_cap_per_island: config.ship_cap_per_island,
+            upkeep_free_ships: config.upkeep_free_ships,
+            ..self.game_state.game_constants.clone()
+        };
+        self.add_to_frame(StateMessage::GameConstants { constants });
+        self.add_to_frame(StateMessage::MatchRules {
+            rules: config.match_rules,
+        });
+    }
+
+    /// The requested team if it exists, otherwise the team with fewer players
+    fn assign_team(&self, requested: Option<u8>) -> Option<u8> {
+        if self.teams == 0 {
+            return None;
+        }
+        if let Some(team) = requested.filter(|&team| team < self.teams) {
+            return Some(team);
+        }
+        let mut counts = vec![0; self.teams as usize];
+        let teams = self
+            .game_state
+            .players
+            .values()
+            .filter_map(|player| player.team);
+        // players created in this frame are not in the state yet
+        let pending = self.frame_inputs.iter().filter_map(|msg| match msg {
+            StateMessage::CreatePlayer { team, .. } => *team,
+            _ => None,
+        });
+        for team in teams.chain(pending) {
+            if let Some(count) = counts.get_mut(team as usize) {
+                *count += 1;
+            }
         }
+        (0..self.teams).min_by_key(|&team| counts[team as usize])
     }
 
     pub fn get_player_count(&self) -> usize {
@@ -95,11 +202,18 @@ impl GameServer {
         self.game_state.players.values().cloned().collect()
     }
 
-    fn add_bot(&mut self) {
+    pub fn add_bot(&mut self, config: BotConfig) {
+        self.add_bot_with_brain(config, config.brain.create(config));
+    }
+
+    /// Adds a bot driven by any BotBrain, it keeps the same brain when it respawns
+    pub fn add_bot_with_brain(&mut self, config: BotConfig, mut brain: Box<dyn BotBrain>) {
         if self.bots.len() >= MAX_BOTS {
             return;
         }
-        let mut bot = BotPlayer::new(self.next_player_id());
+        let id = self.next_player_id();
+        brain.start(id);
+        let mut bot = BotPlayer::new(id, config, brain);
         let max_size = self.game_state.game_map.dim;
         let x = (self.rng.f64() - 0.5) * max_size / 2.0;
         let y = (self.rng.f64() - 0.5) * max_size / 2.0;
@@ -107,19 +221,36 @@ impl GameServer {
             bot.player.create_ship(x, y)
         }
         let name = format!("Bot {}", bot.player.id);
+        let team = self.assign_team(None);
         self.add_to_frame(StateMessage::CreatePlayer {
             id: bot.player.id,
             name,
             flag: PlayerState::get_player_flag(bot.player.id),
+            team,
         });
         self.bots.push(bot);
     }
 
-    fn remove_bot(&mut self, id: u64) {
-        if let Some(bot) = self.bots.iter().find(|bot| bot.player.id == id) {
-            self.add_to_frame(StateMessage::RemovePlayer { id: bot.player.id });
+    /// Player id and config of every bot in the server
+    pub fn bot_configs(&self) -> Vec<(u64, BotConfig)> {
+        self.bots
+            .iter()
+            .map(|bot| (bot.player.id, bot.config))
+            .collect()
+    }
+
+    fn remove_bot(&mut self, id: u64) -> Option<BotPlayer> {
+        let index = self.bots.iter().position(|bot| bot.player.id == id)?;
+        self.add_to_frame(StateMessage::RemovePlayer { id });
+        Some(self.bots.remove(index))
+    }
+
+    /// Takes the bot out and brings it back with a new id and fresh ships
+    fn respawn_bot(&mut self, id: u64) {
+        if let Some(bot) = self.remove_bot(id) {
+            let config = bot.config;
+            self.add_bot_with_brain(config, bot.into_brain());
         }
-        self.bots.retain(|bot| bot.player.id != id);
     }
 
     pub fn next_player_id(&mut self) -> u64 {
@@ -153,7 +284,8 @@ impl GameServer {
                 log::error!("Server should not receive FrameMessage");
             }
             GameMessage::InputMessage(msg) => self.add_to_frame(msg),
-            GameMessage::AddBot => self.add_bot(),
+            // bots without their own config use the one of the server
+            GameMessage::AddBot(config) => self.add_bot(config.unwrap_or(self.bot_config)),
             GameMessage::RemoveBot => {
                 if let Some(bot) = self.bots.first() {
                     self.remove_bot(bot.player.id);
@@ -163,7 +295,7 @@ impl GameServer {
                 if let Some(bot) = self.bots.last_mut() {
                     bot.player.create_ship(x, y);
                 } else {
-                    self.add_bot();
+                    self.add_bot(self.bot_config);
                 }
             }
             GameMessage::AskBroadcast { player } => {
@@ -179,6 +311,7 @@ impl GameServer {
             GameMessage::None => {}
             GameMessage::ConnectionDown => {}
             GameMessage::Reconnection => {}
+            GameMessage::MatchEnded { .. } => {}
         };
     }
 
@@ -188,6 +321,7 @@ impl GameServer {
         id: Option<u64>,
         name: &str,
         flag: Option<String>,
+        team: Option<u8>,
     ) -> u64 {
         if let Some(id) = id {
             if let Some(player) = self.players.get_mut(&id) {
@@ -220,12 +354,11 @@ impl GameServer {
             id,
             name: name.to_string(),
             flag,
+            team: self.assign_team(team),
         };
         self.add_to_frame(create_player_msg.clone());
 
-        let map_size = self.game_state.game_map.dim * 0.8;
-        let start_x = (self.rng.f64() - 0.5) * map_size;
-        let start_y = (self.rng.f64() - 0.5) * map_size;
+        let (start_x, start_y) = self.random_start_position();
 
         self.send_message_to_player(
             id,
@@ -238,23 +371,34 @@ impl GameServer {
 
         self.send_message_to_player(id, GameMessage::Reconnection);
 
-        for _ in 0..PLAYER_START_SHIPS {
-            let mut ship = ShipState::default();
-            ship.position.x = start_x;
-            ship.position.y = start_y;
-            ship.player_id = id;
-            self.add_to_frame(StateMessage::CreateShip { ship });
-        }
+        self.create_start_ships(id, start_x, start_y);
 
         if has_no_players {
             for _ in 0..MAX_BOTS {
-                self.add_bot();
+                self.add_bot(self.bot_config);
             }
         }
 
         return id;
     }
 
+    fn create_start_ships(&mut self, id: u64, x: f64, y: f64) {
+        for _ in 0..PLAYER_START_SHIPS {
+            let mut ship = ShipState::default();
+            ship.position.x = x;
+            ship.position.y = y;
+            ship.player_id = id;
+            self.add_to_frame(StateMessage::CreateShip { ship });
+        }
+    }
+
+    fn random_start_position(&mut self) -> (f64, f64) {
+        let map_size = self.game_state.game_map.dim * 0.8;
+        let x = (self.rng.f64() - 0.5) * map_size;
+        let y = (self.rng.f64() - 0.5) * map_size;
+        (x, y)
+    }
+
     pub fn on_player_connection_down(&mut self, id: u64) {
         info!(
             "Player {} connection down, total players {}",
@@ -285,20 +429,98 @@ impl GameServer {
             .filter(|bot| bot.is_dead())
             .map(|bot| bot.player.id)
             .collect();
-        for bot in dead_bots {
-            self.remove_bot(bot);
-            self.add_bot();
+        // when the last player standing wins, dead bots stay out until the next round
+        if self.game_state.match_state.rules.last_player_standing {
+            return;
+        }
+        for id in dead_bots {
+            self.respawn_bot(id);
+        }
+    }
+
+    fn handle_pirates(&mut self) {
+        let pirates = match self.pirates.as_mut() {
+            Some(pirates) => pirates,
+            None => return,
+        };
+        pirates.tick(&self.game_state);
+        for msg in pirates.player.collect_messages() {
+            self.add_to_frame(msg);
+        }
+    }
+
+    fn handle_match(&mut self) {
+        let match_state = &self.game_state.match_state;
+        let (winner, end_time) = match match_state.phase {
+            MatchPhase::Ended { winner, end_time } => (winner, end_time),
+            MatchPhase::Running => return,
+        };
+        let round = match_state.round;
+        if self.reported_round != Some(round) {
+            self.reported_round = Some(round);
+            self.report_match(winner, round);
+        }
+        if self.game_state.current_time - end_time > ROUND_RESTART_TIME {
+            self.start_new_round();
+        }
+    }
+
+    fn report_match(&mut self, winner: Option<u64>, round: u32) {
+        let players = &self.game_state.players;
+        let winner_state = winner.and_then(|id| players.get(&id)).cloned();
+        info!(
+            "Server {} round {} won by {:?}",
+            self.name,
+            round,
+            winner_state.as_ref().map(|player| &player.name)
+        );
+        let humans = players
+            .values()
+            .filter(|player| self.players.contains_key(&player.id))
+            .cloned()
+            .collect();
+        self.broadcast(GameMessage::MatchEnded { winner, round });
+        let result = MatchResult {
+            server: self.name.clone(),
+            round,
+            seed: self.seed,
+            winner: winner_state,
+            players: humans,
+        };
+        if let Some(sender) = self.db_sender.as_mut() {
+            let msg = DBStatsMessage::MatchResult(Box::new(result));
+            sender.try_send(msg).ok();
+        }
+    }
+
+    fn start_new_round(&mut self) {
+        self.seed = self.rng.u32(..);
+        self.add_to_frame(StateMessage::NewRound { seed: self.seed });
+        let ids: Vec<u64> = self.players.keys().cloned().collect();
+        for id in ids {
+            let (x, y) = self.random_start_position();
+            self.send_message_to_player(id, GameMessage::PlayerCreated { x, y, id });
+            self.create_start_ships(id, x, y);
+        }
+        if let Some(pirates) = self.pirates.as_mut() {
+            *pirates = Pirates::new(pirates.intensity);
+        }
+        let bots: Vec<_> = self.bots.iter().map(|bot| bot.player.id).collect();
+        for id in bots {
+            self.respawn_bot(id);
         }
     }
 
     pub fn tick(&mut self, dt: f64) {
-        if self.players.is_empty() {
+        if self.players.is_empty() && !self.headless {
             return;
         }
 
         self.frames += 1;
 
         self.handle_bots();
+        self.handle_pirates();
+        self.handle_match();
 
         if self.frames % SYNC_EVERY_N_FRAMES == 0 {
             self.remove_inactive_players();
@@ -396,3 +618,57 @@ impl GameServer {
         self.frame_inputs.push(msg);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::GameServer;
+    use crate::{
+        bot_brain::{BotBrain, BotOrder},
+        bot_player::BotConfig,
+        player::Player,
+        server_state::ServerState,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Never orders anything, only writes down the ids it was started and thought with
+    #[derive(Default, Clone)]
+    struct IdleBrain {
+        started: Arc<Mutex<Vec<u64>>>,
+        thought: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl BotBrain for IdleBrain {
+        fn think(&mut self, _game_state: &ServerState, player: &Player) -> Vec<BotOrder> {
+            self.thought.lock().unwrap().push(player.id);
+            vec![]
+        }
+
+        fn start(&mut self, player_id: u64) {
+            self.started.lock().unwrap().push(player_id);
+        }
+    }
+
+    #[test]
+    fn test_custom_brain_survives_respawn() {
+        let mut server = GameServer::new(None, 0);
+        let brain = IdleBrain::default();
+        server.add_bot(BotConfig::default());
+        server.add_bot_with_brain(BotConfig::default(), Box::new(brain.clone()));
+        server.handle_bots();
+        let first_id = brain.started.lock().unwrap()[0];
+        assert_eq!(*brain.thought.lock().unwrap(), vec![first_id]);
+
+        server.respawn_bot(first_id);
+        server.handle_bots();
+        let started = brain.started.lock().unwrap().clone();
+        assert_eq!(started.len(), 2);
+        let new_id = started[1];
+        assert_ne!(new_id, first_id);
+        assert_eq!(*brain.thought.lock().unwrap(), vec![first_id, new_id]);
+
+        let bots: Vec<_> = server.bots.iter().map(|bot| bot.player.id).collect();
+        assert_eq!(bots.len(), 2);
+        assert!(bots.contains(&new_id));
+        assert!(!bots.contains(&first_id));
+    }
+}
@@ -28,7 +28,7 @@ impl LocalClient {
     pub fn new(player_name: String, seed: u32) -> LocalClient {
         let (sender, receiver) = channel(100);
         let mut game = game_server::GameServer::new(None, seed);
-        game.new_connection(sender, None, &player_name, None);
+        game.new_connection(sender, None, &player_name, None, None);
         info!("Local server started");
         LocalClient {
             game,
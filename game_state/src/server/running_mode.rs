@@ -10,6 +10,7 @@ use log::info;
 pub enum RunningEvent {
     MyID(u64),
     PositionChanged(V2D),
+    MatchEnded { winner: Option<u64>, round: u32 },
     Pong,
 }
 
@@ -71,6 +72,10 @@ impl RunningMode {
                 GameMessage::Pong => {
                     self.events.notify(RunningEvent::Pong);
                 }
+                GameMessage::MatchEnded { winner, round } => {
+                    self.events
+                        .notify(RunningEvent::MatchEnded { winner, round });
+                }
                 _ => {}
             }
         }
@@ -109,21 +114,41 @@ impl RunningMode {
 #[cfg(test)]
 mod test {
     use crate::server::{game_server::GameMessage, local_client::LocalClient};
+    use crate::{server_state::ServerState, ship::ShipKey};
 
     #[test]
     fn running_mode() {
         let client = LocalClient::new("test_player".to_string(), 0);
         let mut local = super::RunningMode::new(Box::new(client));
-        local.send_game_message(GameMessage::AddBot);
-        local.send_game_message(GameMessage::AddBot);
-        local.send_game_message(GameMessage::AddBot);
-        local.send_game_message(GameMessage::AddBot);
+        local.send_game_message(GameMessage::AddBot(None));
+        local.send_game_message(GameMessage::AddBot(None));
+        local.send_game_message(GameMessage::AddBot(None));
+        local.send_game_message(GameMessage::AddBot(None));
         for _ in 0..1000 {
             local.tick(0.016)
         }
+        // let the replica catch up on the frames it still buffers
+        while let Some(frame) = local.frame_buffer.pop() {
+            frame
+                .into_iter()
+                .for_each(|msg| local.game_state.on_message(msg));
+        }
         assert_eq!(
             local.game_state.ship_collection.len(),
             local.client.server_state().unwrap().ship_collection.len()
         );
+        // hulks sink on the replica as they do on the server
+        let sinking = |state: &ServerState| -> Vec<ShipKey> {
+            state
+                .ship_collection
+                .iter()
+                .filter(|(_, ship)| ship.is_sinking())
+                .map(|(key, _)| *key)
+                .collect()
+        };
+        assert_eq!(
+            sinking(&local.game_state),
+            sinking(local.client.server_state().unwrap())
+        );
     }
 }
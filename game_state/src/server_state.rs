@@ -1,33 +1,54 @@
 use crate::{
-    bullet::Bullet,
+    boidlike::BoidsTeam,
+    bullet::{AmmoKind, Bullet, CHAIN_SHOT_SLOW_TIME},
+    fort::{Fort, FORT_COST, FORT_SIZE},
     game_map::WorldGrid,
-    hashgrid::HashGrid,
+    hashgrid::{HashEntity, HashEntityKind, HashGrid},
     island::IslandData,
-    player_state::PlayerState,
-    ship::SHIP_SIZE,
+    loot::{
+        Loot, LootKind, Reward, CRATE_SPAWN_AREA, CRATE_SPAWN_CHANCE_PER_SECOND,
+        FAST_RELOAD_DURATION, LOOT_PICKUP_RADIUS, MAX_CRATES, REPAIR_HP, RESOURCES_REWARD,
+    },
+    match_state::{MatchPhase, MatchRules, MatchState},
+    navigation::{plan_paths, plan_waypoints, OrderKind, ShipOrder, ShipPath},
+    pirates::PIRATE_PLAYER_ID,
+    player_state::{PlayerState, TeamStats},
     ship::{ShipKey, ShipState},
+    ship::{SHIP_MAX_HP, SHIP_SIZE},
+    storm::{
+        Storm, MAX_STORMS, STORM_DAMAGE_PER_SECOND, STORM_ERROR_FACTOR,
+        STORM_SPAWN_CHANCE_PER_SECOND,
+    },
+    trade::{TradeRoute, Voyage, MAX_ROUTES_PER_PLAYER, TRADE_INCOME},
     utils::vectors::{V2D, V3D},
     world_gen::{self},
 };
-use cgmath::InnerSpace;
+use cgmath::{InnerSpace, MetricSpace};
 use log::info;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::{borrow::BorrowMut, collections::BTreeMap, sync::Arc};
 use wasm_bindgen::prelude::*;
 
-const TOTAL_HIT: f64 = 30.0;
-const BLAST_RADIUS: f64 = 20.0;
 const EXPLOSION_TTL: f64 = 1.0;
 const SHIP_PRODUCTION_TIME: f64 = 10.0;
 const ISLAND_TAKE_TIME: f64 = 1.0;
-const MAX_PLAYER_SHIPS: usize = 100;
+/// Pirates near an undefended island drain its production this many times faster than it grows
+const PIRATE_RAID_FACTOR: f64 = 2.0;
 pub const PLAYER_START_SHIPS: usize = 20;
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct GameConstants {
     pub wind_speed: (f64, f64, f64),
     pub err_per_m: f64,
+    /// Bullets also damage the ships of the shooter and its allies
+    pub friendly_fire: bool,
+    /// Ships a player can have before owning any island
+    pub ship_cap: usize,
+    /// Extra ships allowed for each island the player owns
+    pub ship_cap_per_island: usize,
+    /// Fleets larger than this slow down the production of their islands, None disables upkeep
+    pub upkeep_free_ships: Option<usize>,
 }
 
 impl Default for GameConstants {
@@ -35,6 +56,10 @@ impl Default for GameConstants {
         Self {
             wind_speed: (0.0, 0.0, 0.0),
             err_per_m: 0.01,
+            friendly_fire: true,
+            ship_cap: 100,
+            ship_cap_per_island: 0,
+            upkeep_free_ships: None,
         }
     }
 }
@@ -45,6 +70,18 @@ impl GameConstants {
         let err = d.magnitude() * self.err_per_m;
         return Some(err);
     }
+
+    pub fn ship_cap(&self, islands: usize) -> usize {
+        self.ship_cap + self.ship_cap_per_island * islands
+    }
+
+    /// Multiplies the production speed of the islands of a player with this many ships
+    pub fn production_factor(&self, ships: usize) -> f64 {
+        match self.upkeep_free_ships {
+            Some(free) if ships > free => free as f64 / ships as f64,
+            _ => 1.0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,9 +108,16 @@ pub struct BroadCastState {
     explosions: BTreeMap<u64, Explosion>,
     game_constants: GameConstants,
     island_dynamic: BTreeMap<u64, IslandDynamicData>,
+    storms: BTreeMap<u64, Storm>,
+    loot: BTreeMap<u64, Loot>,
+    trade_routes: BTreeMap<u64, TradeRoute>,
+    merchants: BTreeMap<ShipKey, Voyage>,
+    ship_paths: BTreeMap<ShipKey, ShipPath>,
+    match_state: MatchState,
     artifact_gen: ArtifactGen,
     current_time: f64,
     rng_seed: u64,
+    seed: u32,
     frame: usize,
 }
 
@@ -85,9 +129,16 @@ impl BroadCastState {
             bullets: BTreeMap::new(),
             explosions: BTreeMap::new(),
             island_dynamic: BTreeMap::new(),
+            storms: BTreeMap::new(),
+            loot: BTreeMap::new(),
+            trade_routes: BTreeMap::new(),
+            merchants: BTreeMap::new(),
+            ship_paths: BTreeMap::new(),
+            match_state: MatchState::default(),
             artifact_gen: ArtifactGen::new(),
             current_time: 5.0,
             rng_seed: 0,
+            seed: 0,
             frame: 0,
             game_constants: GameConstants::default(),
         }
@@ -100,6 +151,7 @@ pub enum StateMessage {
         ship_id: u64,
         player_id: u64,
         target: V2D,
+        ammo: AmmoKind,
     },
     SetPlayerName {
         name: String,
@@ -113,13 +165,22 @@ pub enum StateMessage {
         id: u64,
         player_id: u64,
     },
+    /// Sails each ship to the target of its order along a path the state finds.
+    /// With append the order waits behind the ones the ship already has.
+    MoveShipsTo {
+        player_id: u64,
+        orders: Vec<(u64, ShipOrder)>,
+        avoid_storms: bool,
+        append: bool,
+    },
     BroadCastState {
-        state: BroadCastState,
+        state: Box<BroadCastState>,
     },
     CreatePlayer {
         id: u64,
         name: String,
         flag: String,
+        team: Option<u8>,
     },
     RemovePlayer {
         id: u64,
@@ -127,6 +188,34 @@ pub enum StateMessage {
     GameConstants {
         constants: GameConstants,
     },
+    MatchRules {
+        rules: MatchRules,
+    },
+    CreateTradeRoute {
+        player_id: u64,
+        from: u64,
+        to: u64,
+    },
+    RemoveTradeRoute {
+        id: u64,
+        player_id: u64,
+    },
+    /// A ship reached a wreck or a supply crate
+    CollectLoot {
+        loot_id: u64,
+        ship_id: u64,
+        player_id: u64,
+    },
+    /// Starts building a fort on the lighthouse of an island the player owns,
+    /// paying FORT_COST resources
+    BuildFort {
+        island_id: u64,
+        player_id: u64,
+    },
+    /// Ends the current round and generates a new world with this seed
+    NewRound {
+        seed: u32,
+    },
     Tick(f64),
     None,
 }
@@ -138,6 +227,20 @@ pub enum ExplosionKind {
     Bullet,
     Ship,
     Shot,
+    ChainShot,
+    Grapeshot,
+    HeavyShot,
+}
+
+impl From<AmmoKind> for ExplosionKind {
+    fn from(ammo: AmmoKind) -> Self {
+        match ammo {
+            AmmoKind::RoundShot => ExplosionKind::Bullet,
+            AmmoKind::ChainShot => ExplosionKind::ChainShot,
+            AmmoKind::Grapeshot => ExplosionKind::Grapeshot,
+            AmmoKind::HeavyShot => ExplosionKind::HeavyShot,
+        }
+    }
 }
 
 type ShipCollection = BTreeMap<ShipKey, ShipState>;
@@ -161,11 +264,27 @@ pub struct IslandDynamicData {
     pub id: u64,
     pub lighthouse: V2D,
     pub tiles: usize,
+    /// Players with ships close to the lighthouse
+    pub contesting: Vec<u64>,
+    /// More than one side has ships close to the lighthouse
+    pub contested: bool,
+    pub fort: Option<Fort>,
+}
+
+/// Ships close to an island grouped by alliance
+#[derive(Debug)]
+struct IslandSide {
+    ships: usize,
+    /// Player of the side with more ships, takes the island if the side wins
+    leader: u64,
+    leader_ships: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct ServerFlags {
     pub map_changed: bool,
+    /// A new world was generated, terrain must be rebuilt
+    pub world_changed: bool,
 }
 
 #[derive(Clone)]
@@ -176,6 +295,14 @@ pub struct ServerState {
     pub world_gen: Arc<world_gen::WorldGen>,
     pub bullets: BTreeMap<(u64, u64), Bullet>,
     pub island_dynamic: BTreeMap<u64, IslandDynamicData>,
+    pub storms: BTreeMap<u64, Storm>,
+    pub loot: BTreeMap<u64, Loot>,
+    pub trade_routes: BTreeMap<u64, TradeRoute>,
+    /// Route progress of each merchant ship
+    pub merchants: BTreeMap<ShipKey, Voyage>,
+    /// Paths of the ships sailing to a destination
+    pub ship_paths: BTreeMap<ShipKey, ShipPath>,
+    pub match_state: MatchState,
     pub ship_collection: ShipCollection,
     pub current_time: f64,
     pub game_constants: GameConstants,
@@ -183,6 +310,7 @@ pub struct ServerState {
     rng: fastrand::Rng,
     artifact_gen: ArtifactGen,
     pub flags: ServerFlags,
+    pub seed: u32,
     frame: usize,
 }
 
@@ -200,14 +328,21 @@ impl ServerState {
             players: BTreeMap::new(),
             bullets: BTreeMap::new(),
             island_dynamic: BTreeMap::new(),
+            storms: BTreeMap::new(),
+            loot: BTreeMap::new(),
+            trade_routes: BTreeMap::new(),
+            merchants: BTreeMap::new(),
+            ship_paths: BTreeMap::new(),
+            match_state: MatchState::default(),
             ship_collection: ShipCollection::new(),
-            game_constants: GameConstants {
-                wind_speed: (0.0, 0.0, 0.0),
-                err_per_m: 0.01,
-            },
+            game_constants: GameConstants::default(),
             hash_grid,
             rng: fastrand::Rng::with_seed(0),
-            flags: ServerFlags { map_changed: true },
+            flags: ServerFlags {
+                map_changed: true,
+                world_changed: true,
+            },
+            seed,
             frame: 0,
         };
         me.fill_island_dynamic();
@@ -240,6 +375,48 @@ impl ServerState {
             .collect::<_>()
     }
 
+    /// Replaces the world with a new one generated from the seed,
+    /// everything that lived on the old map is discarded.
+    fn regenerate_world(&mut self, seed: u32) {
+        let world_gen = Arc::new(world_gen::WorldGen::new(seed));
+        self.game_map = Arc::new(world_gen.generate_grid());
+        self.world_gen = world_gen;
+        self.hash_grid = HashGrid::new(self.game_map.dim, self.game_map.tile_size);
+        self.seed = seed;
+        self.ship_collection.clear();
+        self.bullets.clear();
+        self.explosions.clear();
+        self.storms.clear();
+        self.loot.clear();
+        self.trade_routes.clear();
+        self.merchants.clear();
+        self.ship_paths.clear();
+        self.players
+            .values_mut()
+            .for_each(|player| player.ships = 0);
+        self.island_dynamic.clear();
+        self.fill_island_dynamic();
+        self.flags.map_changed = true;
+        self.flags.world_changed = true;
+    }
+
+    fn start_new_round(&mut self, seed: u32) {
+        self.regenerate_world(seed);
+        self.players.values_mut().for_each(|player| {
+            let team = player.team;
+            *player = PlayerState::new(player.name.clone(), player.id, player.flag.clone());
+            player.team = team;
+        });
+        self.match_state.phase = MatchPhase::Running;
+        self.match_state.round += 1;
+        self.match_state.round_start = self.current_time;
+        log::info!(
+            "Round {} started with seed {}",
+            self.match_state.round,
+            seed
+        );
+    }
+
     fn fill_island_dynamic(&mut self) {
         for island in self.game_map.all_island_data() {
             self.island_dynamic.insert(
@@ -251,16 +428,32 @@ impl ServerState {
                     id: island.id,
                     lighthouse: island.light_house.into(),
                     tiles: island.tiles,
+                    contesting: vec![],
+                    contested: false,
+                    fort: None,
                 },
             );
         }
     }
 
-    fn update_hashgrid(&mut self) {
+    pub(crate) fn update_hashgrid(&mut self) {
         let mut hash_grid = HashGrid::new(self.game_map.dim, Bullet::max_distance());
-        for state in self.ship_collection.values() {
+        // sinking hulks can't be targeted or hit anymore
+        for state in self
+            .ship_collection
+            .values()
+            .filter(|ship| !ship.is_sinking())
+        {
             hash_grid.insert(state.to_hash_entity());
         }
+        for island in self.island_dynamic.values() {
+            if island.fort.is_some() {
+                hash_grid.insert(HashEntity {
+                    position: island.lighthouse,
+                    entity: HashEntityKind::Lighthouse(island.id),
+                });
+            }
+        }
         self.hash_grid = hash_grid;
     }
 
@@ -288,7 +481,7 @@ impl ServerState {
 
     pub fn state_message(&self) -> StateMessage {
         StateMessage::BroadCastState {
-            state: self.get_broadcast_state(),
+            state: Box::new(self.get_broadcast_state()),
         }
     }
 
@@ -303,7 +496,14 @@ impl ServerState {
             rng_seed: self.rng.get_seed(),
             game_constants: self.game_constants.clone(),
             island_dynamic: self.island_dynamic.clone(),
-            frame: 0,
+            storms: self.storms.clone(),
+            loot: self.loot.clone(),
+            trade_routes: self.trade_routes.clone(),
+            merchants: self.merchants.clone(),
+            ship_paths: self.ship_paths.clone(),
+            match_state: self.match_state.clone(),
+            seed: self.seed,
+            frame: self.frame,
         }
     }
 
@@ -312,6 +512,9 @@ impl ServerState {
 
         self.current_time += dt;
         let mut explosions = vec![];
+        let mut wrecks = vec![];
+        // (shooter, damage, killed) to give experience to the ships that hit enemies
+        let mut credits = vec![];
 
         self.explosions.retain(|_key, explosion| {
             if self.current_time - explosion.time_created > EXPLOSION_TTL {
@@ -321,6 +524,8 @@ impl ServerState {
         });
 
         let artifact_gen = self.artifact_gen.borrow_mut();
+        let players = &self.players;
+        let friendly_fire = self.game_constants.friendly_fire;
 
         self.bullets.retain(|_key, bullet| {
             bullet.evolve(dt);
@@ -332,18 +537,53 @@ impl ServerState {
             let pos: V3D = bullet.target.into();
 
             self.hash_grid
-                .query_near((pos.x, pos.y).into(), BLAST_RADIUS)
+                .query_near((pos.x, pos.y).into(), bullet.ammo.blast_radius())
                 .filter_map(|entity| {
                     return entity.as_boat();
                 })
                 .for_each(|(key, _)| {
                     if let Some(ship) = self.ship_collection.get_mut(&key) {
+                        let is_ally = allied(players, bullet.player_id, ship.player_id);
+                        if !friendly_fire && is_ally {
+                            return;
+                        }
                         let ship_pos: V3D = (ship.position.x, ship.position.y, 0.0).into();
                         let distance = (ship_pos - pos).magnitude();
-                        ship.hp -= calc_damage(distance);
-                        if ship.hp <= 0.0 && ship.killed_by.is_none() {
+                        let damage = bullet.ammo.damage(distance).min(ship.hp.max(0.0));
+                        ship.hp -= damage;
+                        if bullet.ammo == AmmoKind::ChainShot && damage > 0.0 {
+                            ship.slow_time = CHAIN_SHOT_SLOW_TIME;
+                        }
+                        let killed = ship.hp <= 0.0 && ship.killed_by.is_none();
+                        if killed {
                             ship.killed_by = Some(bullet.player_id);
                         }
+                        if let (Some(ship_id), false) = (bullet.ship_id, is_ally) {
+                            let shooter = ShipKey::new(ship_id, bullet.player_id);
+                            credits.push((shooter, damage, killed));
+                        }
+                    }
+                });
+
+            self.hash_grid
+                .query_near(
+                    (pos.x, pos.y).into(),
+                    bullet.ammo.blast_radius() + FORT_SIZE,
+                )
+                .filter_map(|entity| entity.as_lighthouse())
+                .for_each(|(island_id, lighthouse)| {
+                    let island = match self.island_dynamic.get_mut(&island_id) {
+                        Some(island) => island,
+                        None => return,
+                    };
+                    let is_ally = island
+                        .owner
+                        .is_some_and(|owner| allied(players, owner, bullet.player_id));
+                    if let Some(fort) = island.fort.as_mut() {
+                        if friendly_fire || !is_ally {
+                            let distance = lighthouse.distance(V2D::new(pos.x, pos.y));
+                            fort.hp -= bullet.ammo.damage((distance - FORT_SIZE).max(0.0));
+                        }
                     }
                 });
 
@@ -352,7 +592,7 @@ impl ServerState {
                 id: artifact_gen.next(),
                 time_created: self.current_time,
                 player_id: bullet.player_id,
-                kind: ExplosionKind::Bullet,
+                kind: bullet.ammo.into(),
             };
 
             explosions.push(explosion);
@@ -360,25 +600,46 @@ impl ServerState {
             return false;
         });
 
-        self.ship_collection.retain(|_id, ship| {
-            let position: V2D = ship.position.into();
-            let speed: V2D = ship.speed.into();
-            if speed.magnitude() > 0.001 {
-                let orientation: V2D = ship.orientation.into();
-                let diff = orientation - speed.normalize();
-                let new_orientation = orientation - diff * dt * 5.0;
-                ship.orientation = new_orientation.into();
+        for (shooter, damage, killed) in credits {
+            if let Some(ship) = self.ship_collection.get_mut(&shooter) {
+                ship.add_experience(damage, killed);
             }
-            let position = position + speed * dt;
+        }
 
-            ship.position = position.into();
-            ship.speed = speed.into();
+        self.tick_handle_storms(dt);
+        self.tick_handle_forts(dt);
+        self.tick_handle_loot(dt);
+        self.tick_handle_trade();
+        self.tick_handle_paths();
+        BoidsTeam::new(&mut self.ship_collection).update(self.game_map.dim, self.frame as u64);
+
+        self.ship_collection.retain(|_id, ship| {
+            let current = self.game_map.currents.at(ship.position.x, ship.position.y);
+            ship.evolve(dt, current);
+            let position = ship.position;
 
-            if self.game_map.is_forbidden_land(position.x, position.y) {
-                ship.hp = 0.0;
+            if !ship.is_sinking() {
+                if self.game_map.is_forbidden_land(position.x, position.y) {
+                    ship.hp = 0.0;
+                }
+                if ship.hp > 0.0 {
+                    return true;
+                }
+                ship.start_sinking();
+                if let Some(player) = self.players.get_mut(&ship.player_id) {
+                    player.ships = player.ships.saturating_sub(1);
+                }
+                explosions.push(Explosion {
+                    position: ship.position,
+                    id: self.artifact_gen.next(),
+                    time_created: self.current_time,
+                    player_id: ship.player_id,
+                    kind: ExplosionKind::Ship,
+                });
+                return true;
             }
 
-            if ship.hp > 0.0 {
+            if !ship.sink(dt) {
                 return true;
             }
 
@@ -394,15 +655,14 @@ impl ServerState {
                 player.deaths += 1;
             }
 
-            let explosion = Explosion {
-                position: ship.position,
+            let wreck = Loot {
                 id: self.artifact_gen.next(),
+                position: ship.position,
+                kind: LootKind::Wreck,
+                reward: Reward::random(&mut self.rng),
                 time_created: self.current_time,
-                player_id: ship.player_id,
-                kind: ExplosionKind::Ship,
             };
-
-            explosions.push(explosion);
+            wrecks.push(wreck);
 
             return false;
         });
@@ -411,23 +671,495 @@ impl ServerState {
             self.explosions.insert(explosion.id, explosion);
         }
 
-        self.tick_handle_island_takes(dt);
-        self.tick_handle_ship_production(dt);
+        for wreck in wrecks {
+            self.loot.insert(wreck.id, wreck);
+        }
+
+        if self.match_state.is_running() {
+            self.tick_handle_island_takes(dt);
+            self.tick_handle_ship_production(dt);
+        }
         self.tick_handle_player_stats();
+        self.tick_handle_match();
 
         self.frame += 1;
     }
 
+    fn tick_handle_storms(&mut self, dt: f64) {
+        let current_time = self.current_time;
+        self.storms.retain(|_, storm| {
+            storm.evolve(dt);
+            !storm.is_over(current_time)
+        });
+
+        for ship in self.ship_collection.values_mut() {
+            if ship.is_sinking() {
+                continue;
+            }
+            let in_storm = self
+                .storms
+                .values()
+                .any(|storm| storm.contains(ship.position));
+            if in_storm {
+                ship.hp -= STORM_DAMAGE_PER_SECOND * dt;
+            }
+        }
+
+        if self.storms.len() < MAX_STORMS && self.rng.f64() < STORM_SPAWN_CHANCE_PER_SECOND * dt {
+            let id = self.artifact_gen.next();
+            let storm = Storm::random(id, &mut self.rng, self.game_map.dim, current_time);
+            self.storms.insert(id, storm);
+        }
+    }
+
+    /// Steers the ships along their paths. At the end of a path the ship goes on with its
+    /// next order, or stops and drops the path when there is none left.
+    /// Ships on an attack move hold their position and fire while an enemy is in range.
+    fn tick_handle_paths(&mut self) {
+        let engaged = self.attack_move_targets();
+        let current_time = self.current_time;
+        let mut shots = vec![];
+        let mut finished = vec![];
+        let ships = &mut self.ship_collection;
+        self.ship_paths.retain(|key, path| {
+            let ship = match ships.get_mut(key).filter(|ship| !ship.is_sinking()) {
+                Some(ship) => ship,
+                None => return false,
+            };
+            if let Some(&aim) = engaged.get(key) {
+                ship.target_speed = V2D::new(0.0, 0.0);
+                if ship.find_available_cannon(current_time).is_some() {
+                    shots.push((*key, aim));
+                }
+                return true;
+            }
+            match path.steer(ship) {
+                Some(speed) => ship.target_speed = speed,
+                None => {
+                    ship.target_speed = V2D::new(0.0, 0.0);
+                    finished.push(*key);
+                }
+            }
+            true
+        });
+        for key in finished {
+            self.start_next_order(key);
+        }
+        for (key, (aim, ammo)) in shots {
+            self.handle_shoot(key.id, key.player_id, aim, ammo);
+        }
+    }
+
+    /// Plans the path to the next order in the queue, orders that can't be reached are skipped
+    fn start_next_order(&mut self, key: ShipKey) {
+        let mut path = match self.ship_paths.remove(&key) {
+            Some(path) => path,
+            None => return,
+        };
+        let position = match self.ship_collection.get(&key) {
+            Some(ship) => ship.position,
+            None => return,
+        };
+        // patrols go back in the queue, so we only try each order once
+        for _ in 0..path.orders.len() {
+            let target = match path.next_order() {
+                Some(target) => target,
+                None => return,
+            };
+            if let Some(waypoints) = plan_waypoints(self, position, target, path.avoid_storms) {
+                path.waypoints = waypoints;
+                path.speed_factor = 1.0;
+                self.ship_paths.insert(key, path);
+                return;
+            }
+        }
+    }
+
+    /// Closest enemy each ship on an attack move can hit, with where to aim and the ammo to load
+    fn attack_move_targets(&self) -> BTreeMap<ShipKey, (V2D, AmmoKind)> {
+        let mut targets = BTreeMap::new();
+        let mut seen_storms_of: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        for (key, path) in self.ship_paths.iter() {
+            let order = match path.current_order() {
+                Some(order) if order.kind == OrderKind::AttackMove => order,
+                _ => continue,
+            };
+            let ship = match self.ship_collection.get(key) {
+                Some(ship) => ship,
+                None => continue,
+            };
+            let seen_storms = seen_storms_of
+                .entry(key.player_id)
+                .or_insert_with(|| self.storms_seen_by(key.player_id));
+            let target = self
+                .hash_grid
+                .query_near(ship.position, order.ammo.max_distance())
+                .filter_map(|entity| entity.as_boat())
+                .filter(|(enemy, position)| {
+                    !self.are_allies(key.player_id, enemy.player_id)
+                        && !self.is_hidden_by_storm(*position, seen_storms)
+                })
+                .filter_map(|(enemy, _)| self.ship_collection.get(&enemy))
+                .filter(|enemy| !enemy.is_sinking())
+                .filter_map(|enemy| {
+                    let aim =
+                        Bullet::intercept(ship.position, enemy.position, enemy.speed, order.ammo)?;
+                    Some((ship.position.distance(enemy.position), aim))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((_, aim)) = target {
+                targets.insert(*key, (aim, order.ammo));
+            }
+        }
+        targets
+    }
+
+    fn tick_handle_trade(&mut self) {
+        let island_dynamic = &self.island_dynamic;
+        self.trade_routes.retain(|_, route| {
+            let owns = |island_id| {
+                island_dynamic
+                    .get(&island_id)
+                    .is_some_and(|island| island.owner == Some(route.player_id))
+            };
+            owns(route.from) && owns(route.to)
+        });
+
+        let ships = &self.ship_collection;
+        self.merchants.retain(|key, _| ships.contains_key(key));
+
+        let mut merchants = vec![];
+        for route in self.trade_routes.values_mut() {
+            if !route.should_spawn(self.current_time) {
+                continue;
+            }
+            if let Some(start) = route.start() {
+                route.last_spawn_time = self.current_time;
+                let ship = ShipState {
+                    position: start,
+                    player_id: route.player_id,
+                    merchant: true,
+                    ..Default::default()
+                };
+                merchants.push((ship, route.id));
+            }
+        }
+        for (ship, route_id) in merchants {
+            if let Some(key) = self.create_ship(ship) {
+                let voyage = Voyage {
+                    route_id,
+                    waypoint: 0,
+                };
+                self.merchants.insert(key, voyage);
+            }
+        }
+
+        let mut arrived = vec![];
+        for (key, voyage) in self.merchants.iter_mut() {
+            let ship = match self.ship_collection.get_mut(key) {
+                Some(ship) if !ship.is_sinking() => ship,
+                _ => continue,
+            };
+            // merchants of a lost route drop anchor where they are
+            let route = self.trade_routes.get(&voyage.route_id);
+            let speed = route.map(|route| route.steer(ship.position, &mut voyage.waypoint));
+            match speed {
+                Some(Some(speed)) => ship.target_speed = speed,
+                Some(None) => arrived.push(*key),
+                None => ship.target_speed = V2D::new(0.0, 0.0),
+            }
+        }
+        for key in arrived {
+            self.ship_collection.remove(&key);
+            self.merchants.remove(&key);
+            if let Some(player) = self.players.get_mut(&key.player_id) {
+                player.ships = player.ships.saturating_sub(1);
+                player.resources += TRADE_INCOME;
+            }
+        }
+    }
+
+    fn handle_create_trade_route(&mut self, player_id: u64, from: u64, to: u64) -> Option<()> {
+        let player_routes = self
+            .trade_routes
+            .values()
+            .filter(|route| route.player_id == player_id);
+        if from == to || player_routes.count() >= MAX_ROUTES_PER_PLAYER {
+            return None;
+        }
+        let exists = self
+            .trade_routes
+            .values()
+            .any(|route| route.player_id == player_id && route.from == from && route.to == to);
+        if exists {
+            return None;
+        }
+        let from_island = self.island_dynamic.get(&from)?;
+        let to_island = self.island_dynamic.get(&to)?;
+        if from_island.owner != Some(player_id) || to_island.owner != Some(player_id) {
+            return None;
+        }
+        // lighthouses are on land, the route goes between the closest water tiles
+        let water_near = |position: V2D| {
+            self.game_map
+                .spiral_search(position.x, position.y, |_, _, tile| tile.is_nav_water())
+                .map(V2D::from)
+        };
+        let start = water_near(from_island.lighthouse)?;
+        let end = water_near(to_island.lighthouse)?;
+        let path = self.game_map.find_path(start, end)?;
+        // merchants don't anticipate turns, a rounded route looks less robotic
+        let path = self.game_map.smooth_path(&path);
+        let route = TradeRoute {
+            id: self.artifact_gen.next(),
+            player_id,
+            from,
+            to,
+            path,
+            last_spawn_time: self.current_time,
+        };
+        self.trade_routes.insert(route.id, route);
+        Some(())
+    }
+
+    fn tick_handle_loot(&mut self, dt: f64) {
+        let current_time = self.current_time;
+        self.loot.retain(|_, loot| !loot.is_over(current_time));
+
+        let crates = self
+            .loot
+            .values()
+            .filter(|loot| loot.kind == LootKind::Crate)
+            .count();
+        if crates < MAX_CRATES && self.rng.f64() < CRATE_SPAWN_CHANCE_PER_SECOND * dt {
+            let spawn_size = self.game_map.dim * CRATE_SPAWN_AREA;
+            let position = V2D::new(
+                (self.rng.f64() - 0.5) * spawn_size,
+                (self.rng.f64() - 0.5) * spawn_size,
+            );
+            let reward = Reward::random(&mut self.rng);
+            let is_nav_water = self
+                .game_map
+                .get(position.x, position.y)
+                .is_some_and(|tile| tile.is_nav_water());
+            if is_nav_water {
+                let supply = Loot {
+                    id: self.artifact_gen.next(),
+                    position,
+                    kind: LootKind::Crate,
+                    reward,
+                    time_created: current_time,
+                };
+                self.loot.insert(supply.id, supply);
+            }
+        }
+
+        let pickups: Vec<_> = self
+            .loot
+            .values()
+            .filter_map(|loot| {
+                let (key, _) = self
+                    .hash_grid
+                    .query_near(loot.position, LOOT_PICKUP_RADIUS)
+                    .filter_map(|entity| entity.as_boat())
+                    .find(|(_, position)| loot.can_be_picked_from(*position))?;
+                Some((loot.id, key))
+            })
+            .collect();
+        for (loot_id, key) in pickups {
+            self.on_message(StateMessage::CollectLoot {
+                loot_id,
+                ship_id: key.id,
+                player_id: key.player_id,
+            });
+        }
+    }
+
+    fn handle_collect_loot(&mut self, loot_id: u64, ship_id: u64, player_id: u64) -> Option<()> {
+        let ship = self
+            .ship_collection
+            .get_mut(&ShipKey::new(ship_id, player_id))?;
+        let loot = self.loot.remove(&loot_id)?;
+        match loot.reward {
+            Reward::Repair => {
+                ship.hp = (ship.hp + REPAIR_HP).min(SHIP_MAX_HP);
+            }
+            Reward::FastReload => {
+                ship.reload_boost_until = self.current_time + FAST_RELOAD_DURATION;
+            }
+            Reward::Resources => {
+                let player = self.players.get_mut(&player_id)?;
+                player.resources += RESOURCES_REWARD;
+            }
+        }
+        Some(())
+    }
+
+    fn tick_handle_forts(&mut self, dt: f64) {
+        let mut shots = vec![];
+        for island in self.island_dynamic.values_mut() {
+            let fort = match island.fort.as_mut() {
+                Some(fort) => fort,
+                None => continue,
+            };
+            if fort.is_destroyed() {
+                island.fort = None;
+                let explosion = Explosion {
+                    position: island.lighthouse,
+                    id: self.artifact_gen.next(),
+                    time_created: self.current_time,
+                    player_id: island.owner.unwrap_or_default(),
+                    kind: ExplosionKind::Ship,
+                };
+                self.explosions.insert(explosion.id, explosion);
+                self.flags.map_changed = true;
+                continue;
+            }
+            fort.evolve(dt);
+            if let Some(owner) = island.owner {
+                if fort.can_shoot(self.current_time) {
+                    shots.push((island.id, island.lighthouse, owner));
+                }
+            }
+        }
+        for (island_id, lighthouse, owner) in shots {
+            if let Some(target) = self.fort_target(lighthouse, owner) {
+                self.fort_shoot(island_id, lighthouse, owner, target);
+            }
+        }
+    }
+
+    /// Closest enemy ship the fort can see
+    fn fort_target(&self, lighthouse: V2D, owner: u64) -> Option<V2D> {
+        let seen_storms: Vec<u64> = self
+            .storms
+            .values()
+            .filter(|storm| storm.contains(lighthouse))
+            .map(|storm| storm.id)
+            .collect();
+        self.hash_grid
+            .query_near(lighthouse, Fort::range())
+            .filter_map(|entity| entity.as_boat())
+            .filter(|(key, position)| {
+                !self.are_allies(owner, key.player_id)
+                    && !self.is_hidden_by_storm(*position, &seen_storms)
+            })
+            .map(|(_, position)| position)
+            .min_by(|a, b| a.distance(lighthouse).total_cmp(&b.distance(lighthouse)))
+    }
+
+    fn fort_shoot(&mut self, island_id: u64, lighthouse: V2D, owner: u64, target: V2D) {
+        let error_mod = match self.game_constants.error_margin(target, lighthouse) {
+            Some(error_mod) => error_mod,
+            None => return,
+        };
+        let error_direction: V2D = (self.rng.f64() - 0.5, self.rng.f64() - 0.5).into();
+        let target = error_direction.normalize() * error_mod * self.rng.f64() + target;
+        let current_time = self.current_time;
+        let bullet = self
+            .island_dynamic
+            .get_mut(&island_id)
+            .and_then(|island| island.fort.as_mut())
+            .and_then(|fort| fort.shoot_at(current_time, lighthouse, target, owner));
+        let mut bullet = match bullet {
+            Some(bullet) => bullet,
+            None => return,
+        };
+        bullet.bullet_id = self.artifact_gen.next();
+        self.bullets
+            .insert((bullet.player_id, bullet.bullet_id), bullet);
+        let explosion = Explosion {
+            position: lighthouse,
+            id: self.artifact_gen.next(),
+            time_created: current_time,
+            player_id: owner,
+            kind: ExplosionKind::Shot,
+        };
+        self.explosions.insert(explosion.id, explosion);
+    }
+
+    /// Trade income and crates pay for the fort
+    fn handle_build_fort(&mut self, island_id: u64, player_id: u64) -> Option<()> {
+        let island = self.island_dynamic.get_mut(&island_id)?;
+        let player = self.players.get_mut(&player_id)?;
+        if island.owner != Some(player_id) || island.fort.is_some() {
+            return None;
+        }
+        player.resources = player.resources.checked_sub(FORT_COST)?;
+        island.fort = Some(Fort::default());
+        self.flags.map_changed = true;
+        Some(())
+    }
+
+    pub fn is_in_storm(&self, position: V2D) -> bool {
+        self.storms.values().any(|storm| storm.contains(position))
+    }
+
+    pub fn are_allies(&self, a: u64, b: u64) -> bool {
+        allied(&self.players, a, b)
+    }
+
+    pub fn team_stats(&self) -> Vec<TeamStats> {
+        let mut teams: BTreeMap<u8, TeamStats> = BTreeMap::new();
+        for player in self.players.values() {
+            if let Some(team) = player.team {
+                let stats = teams.entry(team).or_insert_with(|| TeamStats {
+                    team,
+                    ..Default::default()
+                });
+                stats.add(player);
+            }
+        }
+        teams.into_values().collect()
+    }
+
+    /// Storms where the viewer or its allies have ships, everything inside them is visible to the viewer
+    pub fn storms_seen_by(&self, viewer: u64) -> Vec<u64> {
+        self.storms
+            .values()
+            .filter(|storm| {
+                self.ship_collection.values().any(|ship| {
+                    self.are_allies(viewer, ship.player_id) && storm.contains(ship.position)
+                })
+            })
+            .map(|storm| storm.id)
+            .collect()
+    }
+
+    /// Ships inside a storm can only be seen by someone who is also inside it
+    pub fn is_hidden_by_storm(&self, position: V2D, seen_storms: &[u64]) -> bool {
+        self.storms
+            .values()
+            .any(|storm| storm.contains(position) && !seen_storms.contains(&storm.id))
+    }
+
+    pub fn visible_ships(&self, viewer: u64) -> impl Iterator<Item = &ShipState> {
+        let seen_storms = self.storms_seen_by(viewer);
+        self.ship_collection.values().filter(move |ship| {
+            self.are_allies(viewer, ship.player_id)
+                || !self.is_hidden_by_storm(ship.position, &seen_storms)
+        })
+    }
+
+    fn tick_handle_match(&mut self) {
+        if let Some(winner) = self.match_state.check_end(&self.players, self.current_time) {
+            self.match_state.phase = MatchPhase::Ended {
+                winner,
+                end_time: self.current_time,
+            };
+            log::info!(
+                "Round {} ended, winner: {:?}",
+                self.match_state.round,
+                winner
+            );
+        }
+    }
+
     fn tick_handle_player_stats(&mut self) {
         if self.frame % 15 != 0 {
             return;
         }
         self.players.values_mut().for_each(|player| {
-            player.ships = self
-                .ship_collection
-                .values()
-                .filter(|ship| ship.player_id == player.id)
-                .count();
             let mut island_tiles = 0;
             let mut islands = 0;
             self.island_dynamic
@@ -438,6 +1170,7 @@ impl ServerState {
                     island_tiles += island.tiles;
                 });
             player.islands = islands;
+            player.ship_cap = self.game_constants.ship_cap(islands);
             player.percentage_of_map =
                 (island_tiles as f64 / self.game_map.total_island_tiles as f64) * 100.0;
         });
@@ -445,37 +1178,108 @@ impl ServerState {
 
     fn tick_handle_island_takes(&mut self, dt: f64) {
         let progress = dt / ISLAND_TAKE_TIME;
+        let raid = dt / SHIP_PRODUCTION_TIME * PIRATE_RAID_FACTOR;
         let min_distance = self.game_map.tile_size * 2.0;
+        let players = &self.players;
         self.island_dynamic.values_mut().for_each(|island| {
             let island_pos: V2D = island.lighthouse.into();
+            let mut presence: BTreeMap<u64, usize> = BTreeMap::new();
             self.hash_grid
                 .query_near(island_pos, min_distance)
                 .filter_map(|entity| {
-                    return entity
+                    entity
                         .as_boat()
-                        .and_then(|(key, _)| self.ship_collection.get(&key));
+                        .and_then(|(key, _)| self.ship_collection.get(&key))
                 })
-                .for_each(|ship| {
-                    if island.owner != Some(ship.player_id) {
-                        island.take_progress -= progress;
-                        if island.take_progress <= 0.0 {
-                            island.owner = Some(ship.player_id);
-                            self.flags.map_changed = true;
-                        }
-                    } else {
-                        island.take_progress += progress;
-                    }
-                    island.take_progress = island.take_progress.min(1.0).max(0.0);
-                });
+                .for_each(|ship| *presence.entry(ship.player_id).or_insert(0) += 1);
+            // pirates never own islands, they only raid the ones left undefended
+            let has_pirates = presence.remove(&PIRATE_PLAYER_ID).is_some();
+            if has_pirates && presence.is_empty() {
+                island.production_progress = (island.production_progress - raid).max(0.0);
+            }
+            let sides = island_sides(players, &presence);
+            island.contesting = presence.into_keys().collect();
+            island.contested = sides.len() > 1;
+            // tied sides freeze the island
+            let winner = match majority_side(&sides) {
+                Some(winner) => winner,
+                None => return,
+            };
+            let is_owner_side = island
+                .owner
+                .is_some_and(|owner| allied(players, owner, winner));
+            // the fort must be destroyed before the island can be taken
+            if !is_owner_side && island.fort.is_some() {
+                return;
+            }
+            if is_owner_side {
+                island.take_progress += progress;
+            } else {
+                island.take_progress -= progress;
+                if island.take_progress <= 0.0 {
+                    island.owner = Some(winner);
+                    self.flags.map_changed = true;
+                }
+            }
+            island.take_progress = island.take_progress.clamp(0.0, 1.0);
         })
     }
 
+    /// Puts the ship in the closest free water to its position, None when the player
+    /// is at the ship cap or there is no room
+    fn create_ship(&mut self, mut ship: ShipState) -> Option<ShipKey> {
+        if self.is_at_ship_cap(ship.player_id) {
+            return None;
+        }
+        ship.id = self.next_artifact_id();
+        let place =
+            self.game_map
+                .spiral_search(ship.position.x, ship.position.y, |x, y, tile| {
+                    if tile.is_nav_water() {
+                        return !self.is_ship_here(x, y);
+                    }
+                    return false;
+                })?;
+        ship.position = place.into();
+        if let Some(player) = self.players.get_mut(&ship.player_id) {
+            player.ships += 1;
+        }
+        let key = ship.key();
+        self.ship_collection.insert(key, ship);
+        Some(key)
+    }
+
+    /// Ships without a PlayerState, like pirates, have no cap
+    fn is_at_ship_cap(&self, player_id: u64) -> bool {
+        self.players
+            .get(&player_id)
+            .is_some_and(|player| player.ships >= self.game_constants.ship_cap(player.islands))
+    }
+
     fn tick_handle_ship_production(&mut self, dt: f64) {
         let progress_delta = dt / SHIP_PRODUCTION_TIME;
         let mut ships_to_create = vec![];
+        let owner_factors = self
+            .players
+            .values()
+            .map(|player| {
+                let factor = if self.is_at_ship_cap(player.id) {
+                    0.0
+                } else {
+                    self.game_constants.production_factor(player.ships)
+                };
+                (player.id, factor)
+            })
+            .collect::<BTreeMap<_, _>>();
         for island in self.island_dynamic.values_mut() {
+            let is_building_fort = island.fort.as_ref().is_some_and(|fort| !fort.built);
+            if is_building_fort {
+                continue;
+            }
             if let Some(owner) = island.owner {
-                island.production_progress += progress_delta;
+                // production holds while the owner is at the ship cap
+                let factor = owner_factors.get(&owner).copied().unwrap_or(1.0);
+                island.production_progress += progress_delta * factor;
                 if island.production_progress > 1.0 {
                     if let Some(island_data) = self.game_map.islands.get(&island.id) {
                         island.production_progress = 0.0;
@@ -494,6 +1298,7 @@ impl ServerState {
 
     pub fn clear_flags(&mut self) {
         self.flags.map_changed = false;
+        self.flags.world_changed = false;
     }
 
     pub fn get_bullets(&self) -> Vec<&Bullet> {
@@ -523,8 +1328,15 @@ impl ServerState {
             StateMessage::SetPlayerName { name, id } => {
                 self.handle_set_player_name(name, id);
             }
-            StateMessage::CreatePlayer { id, name, flag } => {
-                self.players.insert(id, PlayerState::new(name, id, flag));
+            StateMessage::CreatePlayer {
+                id,
+                name,
+                flag,
+                team,
+            } => {
+                let mut player = PlayerState::new(name, id, flag);
+                player.team = team;
+                self.players.insert(id, player);
             }
             StateMessage::RemovePlayer { id } => {
                 self.players.remove(&id);
@@ -532,12 +1344,16 @@ impl ServerState {
                 self.island_dynamic.iter_mut().for_each(|(_, island)| {
                     if island.owner == Some(id) {
                         island.owner = None;
+                        island.fort = None;
                         self.flags.map_changed = true;
                     }
                 });
                 log::info!("Player {} removed from the server", id);
             }
             StateMessage::BroadCastState { state } => {
+                if state.seed != self.seed {
+                    self.regenerate_world(state.seed);
+                }
                 self.ship_collection = state.ships;
                 self.players = state.players;
                 self.bullets = state.bullets;
@@ -547,33 +1363,18 @@ impl ServerState {
                 self.rng.seed(state.rng_seed);
                 self.game_constants = state.game_constants;
                 self.island_dynamic = state.island_dynamic;
+                self.storms = state.storms;
+                self.loot = state.loot;
+                self.trade_routes = state.trade_routes;
+                self.merchants = state.merchants;
+                self.ship_paths = state.ship_paths;
+                self.match_state = state.match_state;
                 self.flags.map_changed = true;
                 self.frame = state.frame;
                 info!("Broadcast state received");
             }
-            StateMessage::CreateShip { mut ship } => {
-                let player_ships = self
-                    .ship_collection
-                    .values()
-                    .filter(|s| s.player_id == ship.player_id)
-                    .count();
-                if player_ships >= MAX_PLAYER_SHIPS {
-                    return;
-                }
-                ship.id = self.next_artifact_id();
-                if let Some(place) =
-                    self.game_map
-                        .spiral_search(ship.position.x, ship.position.y, |x, y, tile| {
-                            if tile.is_nav_water() {
-                                return !self.is_ship_here(x, y);
-                            }
-                            return false;
-                        })
-                {
-                    ship.position = place.into();
-                    self.ship_collection
-                        .insert(ShipKey::new(ship.id, ship.player_id), ship);
-                }
+            StateMessage::CreateShip { ship } => {
+                self.create_ship(ship);
             }
             StateMessage::MoveShip {
                 id,
@@ -581,36 +1382,112 @@ impl ServerState {
                 player_id,
                 ..
             } => {
-                if let Some(ship) = self.ship_collection.get_mut(&ShipKey { id, player_id }) {
-                    ship.speed = speed;
+                let key = ShipKey { id, player_id };
+                if let Some(ship) = self.ship_collection.get_mut(&key) {
+                    if !ship.is_merchant() && !ship.is_sinking() {
+                        ship.target_speed = speed;
+                        self.ship_paths.remove(&key);
+                    }
+                }
+            }
+            StateMessage::MoveShipsTo {
+                player_id,
+                orders,
+                avoid_storms,
+                append,
+            } => {
+                let mut new_orders = vec![];
+                for (id, order) in orders {
+                    let key = ShipKey::new(id, player_id);
+                    let can_move = self
+                        .ship_collection
+                        .get(&key)
+                        .is_some_and(|ship| !ship.is_merchant() && !ship.is_sinking());
+                    if !can_move {
+                        continue;
+                    }
+                    match self.ship_paths.get_mut(&key).filter(|_| append) {
+                        Some(path) => path.queue(order),
+                        None => new_orders.push((key, order)),
+                    }
+                }
+                for (key, path) in plan_paths(self, &new_orders, avoid_storms) {
+                    self.ship_paths.insert(key, path);
                 }
             }
             StateMessage::Shoot {
                 ship_id,
                 player_id,
                 target,
+                ammo,
             } => {
-                self.handle_shoot(ship_id, player_id, target);
+                self.handle_shoot(ship_id, player_id, target, ammo);
             }
             StateMessage::GameConstants { constants } => {
                 self.game_constants = constants;
             }
+            StateMessage::MatchRules { rules } => {
+                self.match_state.rules = rules;
+            }
+            StateMessage::CreateTradeRoute {
+                player_id,
+                from,
+                to,
+            } => {
+                self.handle_create_trade_route(player_id, from, to);
+            }
+            StateMessage::RemoveTradeRoute { id, player_id } => {
+                self.trade_routes
+                    .retain(|_, route| route.id != id || route.player_id != player_id);
+            }
+            StateMessage::CollectLoot {
+                loot_id,
+                ship_id,
+                player_id,
+            } => {
+                self.handle_collect_loot(loot_id, ship_id, player_id);
+            }
+            StateMessage::BuildFort {
+                island_id,
+                player_id,
+            } => {
+                self.handle_build_fort(island_id, player_id);
+            }
+            StateMessage::NewRound { seed } => {
+                self.start_new_round(seed);
+            }
             StateMessage::None => {}
         }
     }
 
-    fn handle_shoot(&mut self, ship_id: u64, player_id: u64, target: V2D) -> Option<()> {
-        let ship = self
-            .ship_collection
-            .get_mut(&ShipKey::new(ship_id, player_id))?;
-        let pos: V2D = ship.position.into();
+    fn handle_shoot(
+        &mut self,
+        ship_id: u64,
+        player_id: u64,
+        target: V2D,
+        ammo: AmmoKind,
+    ) -> Option<()> {
+        let shooter = self.get_ship(ship_id, player_id)?;
+        let pos: V2D = shooter.position;
+        let accuracy = shooter.accuracy_factor();
         let target: V2D = target.into();
 
-        let error_mod = self.game_constants.error_margin(target, pos)?;
+        let seen_storms = self.storms_seen_by(player_id);
+        if self.is_hidden_by_storm(target, &seen_storms) {
+            return None;
+        }
+        let mut error_mod =
+            self.game_constants.error_margin(target, pos)? * accuracy * ammo.spread();
+        if self.is_in_storm(pos) {
+            error_mod *= STORM_ERROR_FACTOR;
+        }
         let error_direction: V2D = (self.rng.f64() - 0.5, self.rng.f64() - 0.5).into();
         let target = error_direction.normalize() * error_mod * self.rng.f64() + target;
 
-        let mut bullet = ship.shoot_at(self.current_time, target.into())?;
+        let ship = self
+            .ship_collection
+            .get_mut(&ShipKey::new(ship_id, player_id))?;
+        let mut bullet = ship.shoot_at(self.current_time, target.into(), ammo)?;
 
         bullet.bullet_id = self.artifact_gen.next();
 
@@ -631,8 +1508,59 @@ impl ServerState {
     }
 }
 
+fn allied(players: &BTreeMap<u64, PlayerState>, a: u64, b: u64) -> bool {
+    if a == b {
+        return true;
+    }
+    match (players.get(&a), players.get(&b)) {
+        (Some(a), Some(b)) => a.is_ally_of(b),
+        _ => false,
+    }
+}
+
+fn island_sides(
+    players: &BTreeMap<u64, PlayerState>,
+    presence: &BTreeMap<u64, usize>,
+) -> Vec<IslandSide> {
+    let mut sides: Vec<IslandSide> = vec![];
+    for (&player, &ships) in presence {
+        let side = sides
+            .iter_mut()
+            .find(|side| allied(players, side.leader, player));
+        match side {
+            Some(side) => {
+                side.ships += ships;
+                if ships > side.leader_ships {
+                    side.leader = player;
+                    side.leader_ships = ships;
+                }
+            }
+            None => sides.push(IslandSide {
+                ships,
+                leader: player,
+                leader_ships: ships,
+            }),
+        }
+    }
+    sides
+}
+
+/// Leader of the side with more ships than every other side
+fn majority_side(sides: &[IslandSide]) -> Option<u64> {
+    let best = sides.iter().max_by_key(|side| side.ships)?;
+    let tied = sides.iter().filter(|side| side.ships == best.ships).count() > 1;
+    if tied {
+        return None;
+    }
+    Some(best.leader)
+}
+
 #[cfg(test)]
 mod test {
+    use super::{allied, island_sides, majority_side, GameConstants};
+    use crate::player_state::PlayerState;
+    use std::collections::BTreeMap;
+
     #[test]
     fn test_rng() {
         let mut rng = fastrand::Rng::with_seed(0);
@@ -641,12 +1569,53 @@ mod test {
         println!("RNG: {}", rng.f64());
         println!("RNG: {:?}", rng.get_seed());
     }
-}
 
-fn calc_damage(distance: f64) -> f64 {
-    if distance < BLAST_RADIUS {
-        let hit_factor = 1.0 - distance / BLAST_RADIUS;
-        return TOTAL_HIT * hit_factor * hit_factor;
+    #[test]
+    fn test_allies() {
+        let mut players = BTreeMap::new();
+        for (id, team) in [(1, Some(0)), (2, Some(0)), (3, Some(1)), (4, None)] {
+            let mut player = PlayerState::new(format!("p{id}"), id, "br".to_string());
+            player.team = team;
+            players.insert(id, player);
+        }
+        assert!(allied(&players, 1, 2));
+        assert!(!allied(&players, 1, 3));
+        assert!(!allied(&players, 4, 3));
+        assert!(allied(&players, 4, 4));
+    }
+
+    #[test]
+    fn test_island_majority() {
+        let mut players = BTreeMap::new();
+        for (id, team) in [(1, Some(0)), (2, Some(0)), (3, Some(1))] {
+            let mut player = PlayerState::new(format!("p{id}"), id, "br".to_string());
+            player.team = team;
+            players.insert(id, player);
+        }
+        let presence = BTreeMap::from([(1, 2), (2, 3), (3, 4)]);
+        let sides = island_sides(&players, &presence);
+        assert_eq!(sides.len(), 2);
+        assert_eq!(majority_side(&sides), Some(2));
+
+        let presence = BTreeMap::from([(1, 2), (3, 2)]);
+        assert_eq!(majority_side(&island_sides(&players, &presence)), None);
+
+        let presence = BTreeMap::from([(3, 1)]);
+        assert_eq!(majority_side(&island_sides(&players, &presence)), Some(3));
+    }
+
+    #[test]
+    fn test_ship_cap_and_upkeep() {
+        let constants = GameConstants {
+            ship_cap: 20,
+            ship_cap_per_island: 5,
+            upkeep_free_ships: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(constants.ship_cap(0), 20);
+        assert_eq!(constants.ship_cap(4), 40);
+        assert_eq!(constants.production_factor(30), 1.0);
+        assert_eq!(constants.production_factor(60), 0.5);
+        assert_eq!(GameConstants::default().production_factor(1000), 1.0);
     }
-    return 0.0;
 }
@@ -1,13 +1,38 @@
+use cgmath::InnerSpace;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    bullet::Bullet,
+    boidlike::BoidLike,
+    bullet::{AmmoKind, Bullet},
     hashgrid::{HashEntity, HashEntityKind},
     utils::vectors::V2D,
 };
 
 const CANON_RELOAD_TIME: f64 = 5.0;
 pub const SHIP_SIZE: f64 = 10.0;
+pub const SHIP_MAX_HP: f64 = 100.0;
+/// Multiplies the reload time while the ship has a reload boost
+const BOOSTED_RELOAD_FACTOR: f64 = 0.5;
+/// Experience needed for each veterancy rank, experience is damage dealt plus KILL_EXPERIENCE per kill
+const RANK_EXPERIENCE: [f64; 3] = [60.0, 150.0, 300.0];
+const KILL_EXPERIENCE: f64 = 30.0;
+/// Shooting error removed per rank
+const RANK_ACCURACY_BONUS: f64 = 0.15;
+/// Reload time removed per rank
+const RANK_RELOAD_BONUS: f64 = 0.1;
+/// radians per second
+pub const MAX_TURN_RATE: f64 = 1.5;
+/// m/s²
+pub const ACCELERATION: f64 = 4.0;
+/// m/s²
+pub const DECELERATION: f64 = 8.0;
+const MIN_SPEED: f64 = 0.001;
+/// Seconds a destroyed ship takes to go down
+pub const SINKING_TIME: f64 = 5.0;
+/// Multiplies the max speed of ships hit by chain shot
+const CHAIN_SHOT_SLOW_FACTOR: f64 = 0.5;
+/// Speed added by flocking to ships under way, to keep fleets apart
+const FLOCK_SPEED: f64 = 4.0;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord)]
 pub struct ShipKey {
@@ -25,6 +50,8 @@ impl ShipKey {
 pub struct ShipState {
     pub position: V2D,
     pub speed: V2D,
+    /// Velocity commanded by the player, the ship accelerates and turns towards it
+    pub target_speed: V2D,
     pub orientation: V2D,
     pub id: u64,
     pub player_id: u64,
@@ -32,6 +59,20 @@ pub struct ShipState {
     pub last_shoot_time: f64,
     pub hp: f64,
     pub killed_by: Option<u64>,
+    /// The cannons reload faster until this time
+    pub reload_boost_until: f64,
+    pub kills: usize,
+    pub damage_dealt: f64,
+    /// Veterancy rank, from 0 to the number of ranks
+    pub rank: u8,
+    /// Unarmed merchant hull, it sails a trade route and takes no orders
+    pub merchant: bool,
+    /// Seconds left slowed down by chain shot
+    pub slow_time: f64,
+    /// Progress from 0 to 1 of a destroyed ship going down, None while afloat
+    pub sinking: Option<f64>,
+    /// Steering from the ships around it, added to target_speed while under way
+    pub flock_speed: V2D,
 }
 
 impl ShipState {
@@ -53,45 +94,247 @@ impl Default for ShipState {
         Self {
             position: (0.0, 0.0).into(),
             speed: (0.0, 0.0).into(),
+            target_speed: (0.0, 0.0).into(),
             orientation: (1.0, 0.0).into(),
             id: 0,
             player_id: 0,
             cannon_times: [0.0, 0.0, 0.0],
             last_shoot_time: 0.0,
-            hp: 100.0,
+            hp: SHIP_MAX_HP,
             killed_by: None,
+            reload_boost_until: 0.0,
+            kills: 0,
+            damage_dealt: 0.0,
+            rank: 0,
+            merchant: false,
+            slow_time: 0.0,
+            sinking: None,
+            flock_speed: (0.0, 0.0).into(),
         }
     }
 }
 
 impl ShipState {
+    /// Advances the ship physics. The heading turns at most MAX_TURN_RATE towards
+    /// the commanded velocity and the speed changes by ACCELERATION or DECELERATION.
+    /// Only arithmetic and sqrt are used here, so the clients replay it bit for bit.
+    /// The ocean current only drags ships that are under way, anchored ships stay put.
+    pub fn evolve(&mut self, dt: f64, current: V2D) {
+        let target_speed = self.target_speed.magnitude();
+        let current_speed = self.speed.magnitude();
+        let mut desired_speed = 0.0;
+        if target_speed > MIN_SPEED {
+            let steered = self.target_speed + self.flock_speed;
+            let desired_direction = steered / steered.magnitude().max(MIN_SPEED);
+            self.orientation =
+                turn_towards(self.orientation, desired_direction, MAX_TURN_RATE * dt);
+            //we slow down while we are not facing the target
+            let alignment = self.orientation.dot(desired_direction).max(0.0);
+            desired_speed = target_speed * alignment;
+        }
+        if self.slow_time > 0.0 {
+            self.slow_time = (self.slow_time - dt).max(0.0);
+            desired_speed *= CHAIN_SHOT_SLOW_FACTOR;
+        }
+        let new_speed = if desired_speed > current_speed {
+            (current_speed + ACCELERATION * dt).min(desired_speed)
+        } else {
+            (current_speed - DECELERATION * dt).max(desired_speed)
+        };
+        self.speed = self.orientation * new_speed;
+        self.position += self.speed * dt;
+        if target_speed > MIN_SPEED {
+            self.position += current * dt;
+        }
+    }
+
+    /// Radius of the circle the ship does when turning at full rate
+    pub fn turn_radius(&self) -> f64 {
+        self.speed.magnitude() / MAX_TURN_RATE
+    }
+
+    pub fn is_merchant(&self) -> bool {
+        self.merchant
+    }
+
+    pub fn is_sinking(&self) -> bool {
+        self.sinking.is_some()
+    }
+
+    /// The hulk stops taking orders and drifts to a halt while it goes down
+    pub fn start_sinking(&mut self) {
+        self.hp = 0.0;
+        self.target_speed = V2D::new(0.0, 0.0);
+        self.sinking = Some(0.0);
+    }
+
+    /// Advances the sinking progress, true once the ship is gone
+    pub fn sink(&mut self, dt: f64) -> bool {
+        let progress = self.sinking.unwrap_or(0.0) + dt / SINKING_TIME;
+        self.sinking = Some(progress.min(1.0));
+        progress >= 1.0
+    }
+
     pub fn find_available_cannon(&self, current_time: f64) -> Option<usize> {
+        if self.is_merchant() || self.is_sinking() {
+            return None;
+        }
+        let reload_time = CANON_RELOAD_TIME * (1.0 - RANK_RELOAD_BONUS * self.rank as f64);
+        let reload_time = if current_time < self.reload_boost_until {
+            reload_time * BOOSTED_RELOAD_FACTOR
+        } else {
+            reload_time
+        };
         for (i, time) in self.cannon_times.iter().enumerate() {
-            if current_time - time > CANON_RELOAD_TIME {
+            if current_time - time > reload_time {
                 return Some(i);
             }
         }
         None
     }
 
-    pub fn shoot_at(&mut self, current_time: f64, target: V2D) -> Option<Bullet> {
+    /// Heavier ammunition keeps the cannon busy for longer, so its shot time is
+    /// marked ahead by the extra reload.
+    pub fn shoot_at(&mut self, current_time: f64, target: V2D, ammo: AmmoKind) -> Option<Bullet> {
         let cannon_index = self.find_available_cannon(current_time)?;
         let position: V2D = self.position.into();
         let ship_orientation = V2D::from(self.orientation);
         let cannon_multiplier = (cannon_index as i32 - 1) as f64 * SHIP_SIZE / 2.0;
         let cannon_pos = position + ship_orientation * cannon_multiplier;
-        self.mark_shoot_time(cannon_index, current_time);
+        let extra_reload = CANON_RELOAD_TIME * (ammo.reload_factor() - 1.0);
+        self.mark_shoot_time(cannon_index, current_time + extra_reload);
 
         let bullet = Bullet {
             bullet_id: 0,
             player_id: self.player_id,
-            ..Bullet::maybe_from_target(cannon_pos.into(), target.into())?
+            ship_id: Some(self.id),
+            ..Bullet::maybe_from_target_with(cannon_pos.into(), target.into(), ammo)?
         };
         self.last_shoot_time = current_time;
         return Some(bullet);
     }
 
+    /// Multiplies the shooting error of this ship
+    pub fn accuracy_factor(&self) -> f64 {
+        1.0 - RANK_ACCURACY_BONUS * self.rank as f64
+    }
+
+    pub fn add_experience(&mut self, damage: f64, killed: bool) {
+        self.damage_dealt += damage;
+        if killed {
+            self.kills += 1;
+        }
+        let experience = self.damage_dealt + self.kills as f64 * KILL_EXPERIENCE;
+        self.rank = RANK_EXPERIENCE
+            .iter()
+            .filter(|&&needed| experience >= needed)
+            .count() as u8;
+    }
+
     pub fn mark_shoot_time(&mut self, cannon: usize, current_time: f64) {
         self.cannon_times[cannon] = current_time;
     }
 }
+
+impl BoidLike for ShipState {
+    fn position(&self) -> V2D {
+        self.position
+    }
+
+    fn velocity(&self) -> V2D {
+        self.speed
+    }
+
+    /// Ships slowing down to arrive stop flocking, or they could never reach a spot taken by another ship
+    fn is_flocking(&self) -> bool {
+        self.target_speed.magnitude() > FLOCK_SPEED * 2.0
+            && !self.is_merchant()
+            && !self.is_sinking()
+    }
+
+    fn flocks_with(&self, other: &Self) -> bool {
+        self.player_id == other.player_id
+    }
+
+    fn steer(&mut self, direction: V2D) {
+        self.flock_speed = direction * FLOCK_SPEED;
+    }
+}
+
+/// Rotates current towards desired by at most max_step radians.
+/// For small steps, adding the normal and normalizing is close enough to a rotation.
+fn turn_towards(current: V2D, desired: V2D, max_step: f64) -> V2D {
+    let cross = current.perp_dot(desired);
+    let dot = current.dot(desired);
+    if dot > 0.0 && cross.abs() <= max_step {
+        return desired;
+    }
+    let side = if cross >= 0.0 { 1.0 } else { -1.0 };
+    let normal = V2D::new(-current.y, current.x) * side;
+    (current + normal * max_step).normalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ShipState, ACCELERATION, CANON_RELOAD_TIME, MAX_TURN_RATE, SINKING_TIME};
+    use crate::utils::vectors::V2D;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn test_ship_accelerates_and_turns() {
+        let mut ship = ShipState::default();
+        ship.target_speed = V2D::new(0.0, 16.0);
+        let dt = 1.0 / 60.0;
+        ship.evolve(dt, V2D::new(0.0, 0.0));
+        let turned = ship.orientation.angle(V2D::new(1.0, 0.0)).0;
+        assert!(turned <= MAX_TURN_RATE * dt + 1e-9);
+        assert!(ship.speed.magnitude() <= ACCELERATION * dt + 1e-9);
+
+        for _ in 0..600 {
+            ship.evolve(dt, V2D::new(0.0, 0.0));
+        }
+        assert!((ship.speed - ship.target_speed).magnitude() < 1e-6);
+
+        ship.target_speed = V2D::new(0.0, 0.0);
+        for _ in 0..600 {
+            ship.evolve(dt, V2D::new(0.0, 0.0));
+        }
+        assert_eq!(ship.speed.magnitude2(), 0.0);
+    }
+
+    #[test]
+    fn test_veterancy() {
+        let mut ship = ShipState::default();
+        ship.add_experience(50.0, false);
+        assert_eq!(ship.rank, 0);
+        ship.add_experience(10.0, true);
+        assert_eq!(ship.rank, 1);
+        assert!(ship.accuracy_factor() < 1.0);
+
+        ship.mark_shoot_time(0, 10.0);
+        ship.mark_shoot_time(1, 10.0);
+        ship.mark_shoot_time(2, 10.0);
+        assert_eq!(
+            ship.find_available_cannon(10.0 + CANON_RELOAD_TIME * 0.95),
+            Some(0)
+        );
+
+        ship.add_experience(1000.0, false);
+        assert_eq!(ship.rank, 3);
+    }
+
+    #[test]
+    fn test_sinking() {
+        let mut ship = ShipState {
+            target_speed: V2D::new(16.0, 0.0),
+            ..Default::default()
+        };
+        ship.start_sinking();
+        assert!(ship.is_sinking());
+        assert_eq!(ship.find_available_cannon(100.0), None);
+        assert!(!ship.sink(SINKING_TIME / 2.0));
+        assert_eq!(ship.sinking, Some(0.5));
+        assert!(ship.sink(SINKING_TIME / 2.0));
+        assert_eq!(ship.target_speed, V2D::new(0.0, 0.0));
+    }
+}
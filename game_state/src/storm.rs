@@ -0,0 +1,96 @@
+use crate::utils::vectors::V2D;
+use cgmath::{InnerSpace, MetricSpace};
+use serde::{Deserialize, Serialize};
+
+const STORM_SPEED: f64 = 5.0;
+const STORM_MIN_RADIUS: f64 = 150.0;
+const STORM_MAX_RADIUS: f64 = 300.0;
+const STORM_MIN_TTL: f64 = 60.0;
+const STORM_MAX_TTL: f64 = 180.0;
+/// Storms spawn inside this fraction of the map
+const STORM_SPAWN_AREA: f64 = 0.8;
+pub const STORM_DAMAGE_PER_SECOND: f64 = 1.0;
+/// Multiplies the shooting error of ships inside a storm
+pub const STORM_ERROR_FACTOR: f64 = 3.0;
+pub const MAX_STORMS: usize = 3;
+pub const STORM_SPAWN_CHANCE_PER_SECOND: f64 = 0.02;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Storm {
+    pub id: u64,
+    pub position: V2D,
+    pub speed: V2D,
+    pub radius: f64,
+    pub time_created: f64,
+    pub ttl: f64,
+}
+
+impl Storm {
+    pub fn random(id: u64, rng: &mut fastrand::Rng, map_dim: f64, current_time: f64) -> Self {
+        let spawn_size = map_dim * STORM_SPAWN_AREA;
+        let position = V2D::new(
+            (rng.f64() - 0.5) * spawn_size,
+            (rng.f64() - 0.5) * spawn_size,
+        );
+        let direction = V2D::new(rng.f64() - 0.5, rng.f64() - 0.5).normalize();
+        let radius = STORM_MIN_RADIUS + rng.f64() * (STORM_MAX_RADIUS - STORM_MIN_RADIUS);
+        let ttl = STORM_MIN_TTL + rng.f64() * (STORM_MAX_TTL - STORM_MIN_TTL);
+        Self {
+            id,
+            position,
+            speed: direction * STORM_SPEED,
+            radius,
+            time_created: current_time,
+            ttl,
+        }
+    }
+
+    pub fn evolve(&mut self, dt: f64) {
+        self.position += self.speed * dt;
+    }
+
+    pub fn is_over(&self, current_time: f64) -> bool {
+        current_time - self.time_created > self.ttl
+    }
+
+    pub fn contains(&self, position: V2D) -> bool {
+        self.position.distance(position) < self.radius
+    }
+
+    /// Checks if the segment from a to b passes within margin of the storm
+    pub fn intersects_segment(&self, a: V2D, b: V2D, margin: f64) -> bool {
+        self.closest_point_on_segment(a, b).distance(self.position) < self.radius + margin
+    }
+
+    pub fn closest_point_on_segment(&self, a: V2D, b: V2D) -> V2D {
+        let ab = b - a;
+        let length2 = ab.magnitude2();
+        if length2 == 0.0 {
+            return a;
+        }
+        let t = ((self.position - a).dot(ab) / length2).clamp(0.0, 1.0);
+        a + ab * t
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Storm;
+    use crate::utils::vectors::V2D;
+
+    #[test]
+    fn test_segment_intersection() {
+        let storm = Storm {
+            id: 1,
+            position: V2D::new(0.0, 0.0),
+            speed: V2D::new(0.0, 0.0),
+            radius: 10.0,
+            time_created: 0.0,
+            ttl: 10.0,
+        };
+        assert!(storm.intersects_segment(V2D::new(-20.0, 5.0), V2D::new(20.0, 5.0), 0.0));
+        assert!(!storm.intersects_segment(V2D::new(-20.0, 15.0), V2D::new(20.0, 15.0), 0.0));
+        assert!(storm.intersects_segment(V2D::new(-20.0, 15.0), V2D::new(20.0, 15.0), 10.0));
+        assert!(!storm.intersects_segment(V2D::new(20.0, 0.0), V2D::new(40.0, 0.0), 0.0));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::utils::vectors::V2D;
+use cgmath::InnerSpace;
+use serde::{Deserialize, Serialize};
+
+/// Seconds between two merchants leaving on the same route
+const MERCHANT_SPAWN_TIME: f64 = 20.0;
+pub const MERCHANT_SPEED: f64 = 12.0;
+/// Resources delivered by each merchant that arrives
+pub const TRADE_INCOME: usize = 20;
+const WAYPOINT_TOLERANCE: f64 = 5.0;
+pub const MAX_ROUTES_PER_PLAYER: usize = 3;
+
+/// Route between two islands of the same player, merchants sail it delivering income
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradeRoute {
+    pub id: u64,
+    pub player_id: u64,
+    pub from: u64,
+    pub to: u64,
+    /// Water path between the lighthouses, from WorldGrid::find_path
+    pub path: Vec<V2D>,
+    pub last_spawn_time: f64,
+}
+
+/// Where a merchant is on its route
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Voyage {
+    pub route_id: u64,
+    /// Index of the route waypoint the merchant is sailing to
+    pub waypoint: usize,
+}
+
+impl TradeRoute {
+    pub fn should_spawn(&self, current_time: f64) -> bool {
+        current_time - self.last_spawn_time > MERCHANT_SPAWN_TIME
+    }
+
+    pub fn start(&self) -> Option<V2D> {
+        self.path.first().copied()
+    }
+
+    /// Velocity towards the waypoint, the waypoint advances when we get close to it.
+    /// None when the merchant arrived.
+    pub fn steer(&self, position: V2D, waypoint: &mut usize) -> Option<V2D> {
+        loop {
+            let target = *self.path.get(*waypoint)?;
+            let direction = target - position;
+            if direction.magnitude() > WAYPOINT_TOLERANCE {
+                return Some(direction.normalize() * MERCHANT_SPEED);
+            }
+            *waypoint += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TradeRoute, MERCHANT_SPEED};
+    use crate::utils::vectors::V2D;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn test_merchant_follows_route() {
+        let route = TradeRoute {
+            id: 1,
+            player_id: 1,
+            from: 1,
+            to: 2,
+            path: vec![
+                V2D::new(0.0, 0.0),
+                V2D::new(100.0, 0.0),
+                V2D::new(100.0, 100.0),
+            ],
+            last_spawn_time: 0.0,
+        };
+        let mut waypoint = 0;
+        let speed = route.steer(V2D::new(0.0, 0.0), &mut waypoint).unwrap();
+        assert_eq!(waypoint, 1);
+        assert!((speed - V2D::new(MERCHANT_SPEED, 0.0)).magnitude() < 1e-9);
+        let speed = route.steer(V2D::new(99.0, 0.0), &mut waypoint).unwrap();
+        assert_eq!(waypoint, 2);
+        assert!(speed.y > 0.0);
+        assert_eq!(route.steer(V2D::new(100.0, 99.0), &mut waypoint), None);
+    }
+}
@@ -1,3 +1,10 @@
+use crate::bot_brain::BotBrainKind;
+use crate::bot_player::{BotConfig, BotDifficulty, BotPersonality};
+use crate::bullet::AmmoKind;
+use crate::currents::CurrentField;
+use crate::formation::Formation;
+use crate::influence::InfluenceMap;
+use crate::navigation::OrderKind;
 use crate::player::Player;
 use crate::player_state::PlayerState;
 use crate::server::game_server::*;
@@ -65,10 +72,28 @@ impl GameWasmState {
             .shoot_at(&V2D::new(x, y), self.running_mode.server_state());
     }
 
+    pub fn set_ammo(&mut self, ammo: AmmoKind) {
+        self.player.ammo = ammo;
+    }
+
+    pub fn set_formation(&mut self, formation: Formation) {
+        self.player.formation = formation;
+    }
+
     pub fn has_map_changed(&self) -> bool {
         self.running_mode.server_state().flags.map_changed
     }
 
+    pub fn has_world_changed(&self) -> bool {
+        self.running_mode.server_state().flags.world_changed
+    }
+
+    pub fn match_info(&self) -> JsValue {
+        let state = self.running_mode.server_state();
+        let info = state.match_state.info(state.current_time);
+        serde_wasm_bindgen::to_value(&info).unwrap_or_default()
+    }
+
     pub fn clear_flags(&mut self) {
         self.running_mode.clear_flags();
     }
@@ -118,12 +143,16 @@ impl GameWasmState {
         serde_wasm_bindgen::to_value(players).unwrap_or_default()
     }
 
+    pub fn get_team_stats(&self) -> JsValue {
+        let teams = self.running_mode.server_state().team_stats();
+        serde_wasm_bindgen::to_value(&teams).unwrap_or_default()
+    }
+
     pub fn get_all_ship_pos_of_player(&self, id: f64) -> Vec<f64> {
         let ships = self
             .running_mode
             .server_state()
-            .ship_collection
-            .values()
+            .visible_ships(self.player.id)
             .filter(|ship| ship.player_id == id as u64)
             .flat_map(|ship| [ship.position.x, ship.position.y])
             .collect::<Vec<_>>();
@@ -135,8 +164,7 @@ impl GameWasmState {
         let ships: Vec<_> = self
             .running_mode
             .server_state()
-            .ship_collection
-            .values()
+            .visible_ships(self.player.id)
             .filter(|ship| {
                 if ship.player_id != id {
                     return false;
@@ -153,8 +181,7 @@ impl GameWasmState {
         let ships: Vec<_> = self
             .running_mode
             .server_state()
-            .ship_collection
-            .values()
+            .visible_ships(self.player.id)
             .filter(|ship| ship.player_id == id as u64)
             .collect();
 
@@ -176,6 +203,16 @@ impl GameWasmState {
         serde_wasm_bindgen::to_value(&explosions).unwrap_or_default()
     }
 
+    pub fn get_all_storms(&self) -> JsValue {
+        let storms: Vec<_> = self.running_mode.server_state().storms.values().collect();
+        serde_wasm_bindgen::to_value(&storms).unwrap_or_default()
+    }
+
+    pub fn get_all_loot(&self) -> JsValue {
+        let loot: Vec<_> = self.running_mode.server_state().loot.values().collect();
+        serde_wasm_bindgen::to_value(&loot).unwrap_or_default()
+    }
+
     pub fn start_local_server(&mut self, client: LocalClient) {
         self.running_mode = RunningMode::new(Box::new(client));
         self.player = Player::new(self.running_mode.id());
@@ -192,7 +229,6 @@ impl GameWasmState {
         }
         let dt = time - self.current_time;
         self.current_time = time;
-        self.player.tick(&self.running_mode.server_state());
         while let Some(action) = self.player.next_message() {
             self.send_message(action);
         }
@@ -204,6 +240,7 @@ impl GameWasmState {
             constants: GameConstants {
                 wind_speed: (0.0, 0.0, 0.0),
                 err_per_m: err,
+                ..self.running_mode.server_state().game_constants.clone()
             },
         });
     }
@@ -213,6 +250,23 @@ impl GameWasmState {
             .move_selected_ships(&self.running_mode.server_state(), x, y);
     }
 
+    pub fn order_selected_ships(&mut self, x: f64, y: f64, kind: OrderKind, append: bool) {
+        self.player
+            .order_selected_ships(&self.running_mode.server_state(), x, y, kind, append);
+    }
+
+    /// Path and queued orders of each of our ships that has some, to draw them
+    pub fn get_ship_orders(&self) -> JsValue {
+        let state = self.running_mode.server_state();
+        let orders: Vec<_> = state
+            .ship_paths
+            .iter()
+            .filter(|(key, _)| key.player_id == self.player.id)
+            .map(|(key, path)| (key.id, path))
+            .collect();
+        serde_wasm_bindgen::to_value(&orders).unwrap_or_default()
+    }
+
     pub fn action_clear_selected(&mut self) {
         self.player.clear_selected_ships();
     }
@@ -250,8 +304,49 @@ impl GameWasmState {
         self.player.create_ship(x, y);
     }
 
+    pub fn action_build_fort(&mut self, island_id: f64) {
+        self.player.build_fort(island_id as u64);
+    }
+
+    pub fn action_create_trade_route(&mut self, from: f64, to: f64) {
+        self.player.create_trade_route(from as u64, to as u64);
+    }
+
+    pub fn get_trade_routes(&self) -> JsValue {
+        let routes: Vec<_> = self
+            .running_mode
+            .server_state()
+            .trade_routes
+            .values()
+            .collect();
+        serde_wasm_bindgen::to_value(&routes).unwrap_or_default()
+    }
+
+    /// Debug overlay of the strengths around the map as the local player sees them
+    pub fn get_influence_map(&self) -> JsValue {
+        let state = self.running_mode.server_state();
+        let map = InfluenceMap::compute(state, self.player.id);
+        serde_wasm_bindgen::to_value(&map).unwrap_or_default()
+    }
+
     pub fn add_bot(&mut self) {
-        self.running_mode.send_game_message(GameMessage::AddBot)
+        self.running_mode
+            .send_game_message(GameMessage::AddBot(None))
+    }
+
+    pub fn add_bot_with(
+        &mut self,
+        difficulty: BotDifficulty,
+        personality: BotPersonality,
+        brain: BotBrainKind,
+    ) {
+        let config = BotConfig {
+            difficulty,
+            personality,
+            brain,
+        };
+        self.running_mode
+            .send_game_message(GameMessage::AddBot(Some(config)))
     }
 
     pub fn remove_bot(&mut self) {
@@ -262,8 +357,7 @@ impl GameWasmState {
         let ships: Vec<&ShipState> = self
             .running_mode
             .server_state()
-            .ship_collection
-            .values()
+            .visible_ships(self.player.id)
             .filter(|&state| {
                 let distance = V2D::from(state.position).distance(V2D::new(x, y));
                 distance < TOO_FAR
@@ -301,18 +395,43 @@ impl GameWasmState {
         serde_wasm_bindgen::to_value(&islands).unwrap_or_default()
     }
 
+    /// RGBA texture with the ocean height, the distance to the coast
+    /// and the x and y of the ocean current, where 128 means no current.
     pub fn ocean_data(&self, size: usize) -> Vec<u8> {
         let height_map = self.make_ocean_height_map(size);
         let distance_map = self.make_coast_distance_map(size, &height_map);
+        let current_map = self.make_current_map(size);
         height_map
             .into_iter()
             .zip(distance_map.into_iter())
-            .flat_map(|(height, distance)| {
-                return [height, distance, 0, 0];
+            .zip(current_map.into_iter())
+            .flat_map(|((height, distance), (current_x, current_y))| {
+                return [height, distance, current_x, current_y];
             })
             .collect()
     }
 
+    fn make_current_map(&self, size: usize) -> Vec<(u8, u8)> {
+        let mut map = vec![(128, 128); size * size];
+        let half_size = self.map_size() / 2.0;
+        let scale_dimensions =
+            linear_scale_from_points(0.0, -half_size, size as f64 - 1.0, half_size);
+        let max_current = CurrentField::max_strength();
+        let scale = linear_scale_from_points(-max_current, 0.0, max_current, 255.0);
+        let currents = &self.running_mode.server_state().game_map.currents;
+        for i in 0..size {
+            for j in 0..size {
+                let x = scale_dimensions(i as f64);
+                let y = scale_dimensions(j as f64);
+                let current = currents.at(x, y);
+                let current_x = scale(current.x).round().min(255.0).max(0.0);
+                let current_y = scale(current.y).round().min(255.0).max(0.0);
+                map[i + j * size] = (current_x as u8, current_y as u8);
+            }
+        }
+        map
+    }
+
     fn make_ocean_height_map(&self, size: usize) -> Vec<u8> {
         let mut map = vec![0; size * size];
         let min_max = self.min_max_height();
@@ -1,5 +1,6 @@
 use super::game_noise::GameNoise;
 use crate::{
+    currents::CurrentField,
     game_map::{Tile, TileKind, WorldGrid},
     game_noise::NoiseConfig,
     utils::interpolation::LinearInterpolation,
@@ -12,6 +13,7 @@ pub struct WorldGen {
     low_land: GameNoise,
     high_land: GameNoise,
     forest: GameNoise,
+    currents: GameNoise,
     pub config: WorldGenConfig,
     matrix: Matrix3<f64>,
     terrain_interpolation: LinearInterpolation,
@@ -131,6 +133,7 @@ impl WorldGen {
             low_land: GameNoise::new(Some(seed)),
             high_land: GameNoise::new(Some(seed)),
             forest: GameNoise::new(Some(seed)),
+            currents: GameNoise::new(Some(seed.wrapping_add(1))),
             config,
             matrix: Matrix3::identity(),
         }
@@ -227,6 +230,7 @@ impl WorldGen {
             .collect();
         grid.data = data;
         grid.update_map();
+        grid.currents = CurrentField::new(&grid, &self.currents);
         grid
     }
 }